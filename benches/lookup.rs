@@ -0,0 +1,57 @@
+//! Times lookups, printing the mean time each takes.
+//!
+//! Run with `cargo bench`. There is no benchmark framework among the
+//! dependencies, so this is a plain binary that times a fixed number of
+//! lookups with `Instant` after a warm-up run.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use consistent_hashing::{sample_keys, HashRing};
+
+/// Calls `lookup` on every key, once to warm up and then timed, returning
+/// the mean time per call.
+fn time_per_lookup<F: FnMut(u64) -> Option<u64>>(keys: &[u64], mut lookup: F) -> Duration {
+    for &key in keys {
+        black_box(lookup(black_box(key)));
+    }
+    let start = Instant::now();
+    for &key in keys {
+        black_box(lookup(black_box(key)));
+    }
+    start.elapsed() / keys.len() as u32
+}
+
+fn report(name: &str, per_lookup: Duration) {
+    println!("{name:<40} {:>10.1?} per lookup", per_lookup);
+}
+
+/// The successor search `get_node` did before it used the B-tree's range
+/// lookup: a scan of every point, falling back to the lowest.
+fn linear_scan(ring: &HashRing<u64>, key: u64) -> Option<u64> {
+    let hash = ring.hash_one(&key);
+    ring.iter()
+        .find(|(&position, _)| position >= hash)
+        .or_else(|| ring.iter().next())
+        .map(|(_, &node)| node)
+}
+
+fn main() {
+    let mut ring = HashRing::new();
+    ring.add_nodes(sample_keys(1, 100_000));
+    let keys = sample_keys(2, 2_000);
+
+    let scan = time_per_lookup(&keys, |key| linear_scan(&ring, key));
+    let range = time_per_lookup(&keys, |key| ring.get_node(&key).copied());
+    report("100k nodes, linear scan", scan);
+    report("100k nodes, HashRing::get_node", range);
+
+    let frozen = ring.clone().freeze();
+    let bisect = time_per_lookup(&keys, |key| frozen.get_node(&key).copied());
+    report("100k nodes, FrozenRing::get_node", bisect);
+
+    // 2^17 entries, a little more than one per point.
+    let jumped = frozen.with_jump_table(17);
+    let jump = time_per_lookup(&keys, |key| jumped.get_node(&key).copied());
+    report("100k nodes, FrozenRing with a jump table", jump);
+}
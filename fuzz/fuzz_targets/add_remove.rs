@@ -0,0 +1,46 @@
+//! Adds and removes nodes in the order the input says, checking the ring
+//! against a plain set of the nodes that should be on it after every step.
+//!
+//! Each input byte is one step: the low four bits pick one of sixteen
+//! nodes, and the top bit says whether to add it or remove it. Run with
+//! `cargo fuzz run add_remove` from the crate root.
+
+#![no_main]
+
+use std::collections::BTreeSet;
+
+use consistent_hashing::HashRing;
+use libfuzzer_sys::fuzz_target;
+
+const REPLICAS: usize = 8;
+
+fuzz_target!(|steps: &[u8]| {
+    let mut ring = HashRing::with_replicas(REPLICAS);
+    let mut model = BTreeSet::new();
+
+    for &step in steps {
+        let node = step & 0x0f;
+        if step & 0x80 == 0 {
+            assert_eq!(ring.add_node(node), model.insert(node));
+        } else {
+            assert_eq!(ring.remove_node(&node), model.take(&node));
+        }
+
+        assert!(ring.nodes().eq(model.iter()), "membership diverged");
+        assert_eq!(ring.len(), model.len());
+        if let Err(problem) = ring.check_invariants() {
+            panic!("{problem}");
+        }
+
+        // However it got here, the ring has to be the one its nodes make
+        // when added to an empty ring, and route every key the same way.
+        let mut rebuilt = HashRing::with_replicas(REPLICAS);
+        rebuilt.add_nodes(model.iter().copied());
+        assert!(ring == rebuilt, "layout depends on history");
+        for key in 0..64u32 {
+            let owner = ring.get_node(&key);
+            assert_eq!(owner, rebuilt.get_node(&key));
+            assert_eq!(owner.is_some(), !model.is_empty());
+        }
+    }
+});
@@ -0,0 +1,81 @@
+use crate::rendezvous::ln;
+
+/// Returns a replica count that should keep the busiest of `node_count`
+/// evenly weighted nodes within `target_imbalance` times the mean share of
+/// keys, the figure [`HashRing::imbalance_factor`] reports.
+///
+/// A node with `r` replicas owns the sum of `r` arcs, whose spread about
+/// the mean share shrinks as `1 / sqrt(r)`, and the busiest of `n` nodes
+/// sits some `sqrt(2 ln n)` spreads above it. The estimate adds one more
+/// spread as a margin, so most rings built with the count meet the target,
+/// at the price of asking for more replicas than the luckiest need. Each
+/// halving of the excess over `1.0` takes four times the replicas. One node
+/// or none is always balanced, and needs one replica.
+///
+/// [`HashRing::imbalance_factor`]: crate::HashRing::imbalance_factor
+///
+/// # Panics
+///
+/// Panics if `target_imbalance` is not greater than `1.0`, which no ring
+/// of more than one node can promise.
+pub fn recommended_replicas(node_count: usize, target_imbalance: f64) -> usize {
+    assert!(
+        target_imbalance > 1.0,
+        "an imbalance target has to be greater than one"
+    );
+    if node_count <= 1 {
+        return 1;
+    }
+
+    let spreads = 1.0 + sqrt(2.0 * ln(node_count as f64));
+    let excess = target_imbalance - 1.0;
+    let replicas = spreads * spreads / (excess * excess);
+    // Rounds up, saturating at `usize::MAX` for absurdly tight targets.
+    let whole = replicas as usize;
+    if (whole as f64) < replicas {
+        whole.saturating_add(1)
+    } else {
+        whole.max(1)
+    }
+}
+
+/// Returns the square root of a positive, finite `x`, by Newton's method
+/// since `f64::sqrt` needs `std`.
+fn sqrt(x: f64) -> f64 {
+    // Halving the exponent gives a start within a factor of two of the
+    // root, which each step then doubles the correct digits of.
+    let mut root = f64::from_bits((x.to_bits() >> 1) + (1023 << 51));
+    for _ in 0..6 {
+        root = 0.5 * (root + x / root);
+    }
+    root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sample_keys, HashRing};
+
+    #[test]
+    fn rings_built_with_the_recommended_count_meet_the_target() {
+        for (node_count, target) in [(10, 1.5), (20, 1.25), (50, 2.0)] {
+            let replicas = recommended_replicas(node_count, target);
+            let mut missed = 0;
+            for seed in 0..10 {
+                let mut ring = HashRing::with_replicas(replicas);
+                ring.add_nodes(sample_keys(121 + seed, node_count));
+                let imbalance = ring.imbalance_factor();
+                // The margin is one spread, so a ring missing the target
+                // misses it by little, and few do.
+                assert!(imbalance < target + (target - 1.0) / 2.0, "{imbalance}");
+                if imbalance > target {
+                    missed += 1;
+                }
+            }
+            assert!(missed <= 2, "{node_count} nodes, {replicas} replicas");
+        }
+
+        assert_eq!(recommended_replicas(1, 1.01), 1);
+        assert!(recommended_replicas(10, 1.25) >= 4 * recommended_replicas(10, 1.5) - 1);
+    }
+}
@@ -0,0 +1,149 @@
+use alloc::{sync::Arc, vec::Vec};
+use core::hash::Hash;
+
+use crate::{DefaultHashBuilder, HashRing, ReplicaKey, RingHasher};
+
+/// Configures a [`HashRing`] before any nodes are placed on it.
+///
+/// Created with [`HashRing::builder`]. Nodes given to the builder are only
+/// hashed in [`build`](Self::build), once the replica count and hasher are
+/// final.
+#[derive(Debug, Clone)]
+pub struct HashRingBuilder<T, S = DefaultHashBuilder> {
+    replicas: usize,
+    hasher: S,
+    salt: u64,
+    double_hashing: bool,
+    nodes: Vec<(T, u32)>,
+    replica_key: Option<ReplicaKey<T>>,
+}
+
+impl<T> HashRingBuilder<T> {
+    pub(crate) fn new() -> Self {
+        HashRingBuilder {
+            replicas: 1,
+            hasher: DefaultHashBuilder::default(),
+            salt: 0,
+            double_hashing: false,
+            nodes: Vec::new(),
+            replica_key: None,
+        }
+    }
+}
+
+impl<T: Hash + Ord + Clone, S: RingHasher> HashRingBuilder<T, S> {
+    /// Sets the number of points each node is placed at, per unit of weight.
+    pub fn replicas(mut self, replicas: usize) -> Self {
+        self.replicas = replicas;
+        self
+    }
+
+    /// Sets the hasher the ring places nodes and routes keys with.
+    pub fn hasher<S2: RingHasher>(self, hasher: S2) -> HashRingBuilder<T, S2> {
+        HashRingBuilder {
+            replicas: self.replicas,
+            hasher,
+            salt: self.salt,
+            double_hashing: self.double_hashing,
+            nodes: self.nodes,
+            replica_key: self.replica_key,
+        }
+    }
+
+    /// Sets the salt mixed into the ring's hashing, as
+    /// [`HashRing::with_salt`] does.
+    pub fn salt(mut self, salt: u64) -> Self {
+        self.salt = salt;
+        self
+    }
+
+    /// Sets whether a node's points are placed by double hashing: the first
+    /// where it would be anyway, and each of the rest a fixed, odd step
+    /// further round the ring, with the step hashed from the node too.
+    ///
+    /// Hashing every `(node, replica)` pair on its own relies on the hasher
+    /// to scatter near-identical inputs, and a weak one can cluster a node's
+    /// points together. Stepping keeps each node's points spread evenly
+    /// round the ring, and never lets two of them coincide, at the cost of
+    /// no longer matching rings that hash each pair. Off by default, and
+    /// ignored when a [`replica_key`](Self::replica_key) is set.
+    pub fn double_hashing(mut self, double_hashing: bool) -> Self {
+        self.double_hashing = double_hashing;
+        self
+    }
+
+    /// Sets what gets hashed for each of a node's points: the bytes
+    /// `replica_key` returns for the node and the replica's number, counting
+    /// from zero.
+    ///
+    /// By default the ring hashes the `(node, replica)` pair, which no other
+    /// implementation does. Naming replicas the way another system does, say
+    /// as `"node#0"`, `"node#1"` and so on, is the first step to laying out
+    /// the same ring it does; the hasher has to match as well. Keys are not
+    /// affected.
+    pub fn replica_key<F>(mut self, replica_key: F) -> Self
+    where
+        F: Fn(&T, usize) -> Vec<u8> + Send + Sync + 'static,
+    {
+        self.replica_key = Some(ReplicaKey(Arc::new(replica_key)));
+        self
+    }
+
+    /// Adds `node` to the ring being built.
+    pub fn node(self, node: T) -> Self {
+        self.weighted_node(node, 1)
+    }
+
+    /// Adds `node` with `weight` to the ring being built, as
+    /// [`HashRing::add_weighted_node`] would.
+    pub fn weighted_node(mut self, node: T, weight: u32) -> Self {
+        self.nodes.push((node, weight));
+        self
+    }
+
+    /// Adds every node in `nodes` to the ring being built.
+    pub fn nodes<I: IntoIterator<Item = T>>(mut self, nodes: I) -> Self {
+        self.nodes.extend(nodes.into_iter().map(|node| (node, 1)));
+        self
+    }
+
+    /// Builds the ring and places every node added so far on it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the replica count or any node's weight is zero.
+    pub fn build(self) -> HashRing<T, S> {
+        let mut hash_ring = HashRing::with_replicas_and_hasher(self.replicas, self.hasher);
+        hash_ring.salt = self.salt;
+        hash_ring.double_hashing = self.double_hashing;
+        hash_ring.replica_key = self.replica_key;
+        for (node, weight) in self.nodes {
+            hash_ring.add_weighted_node(node, weight);
+        }
+        hash_ring
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::Identity;
+    use crate::HashRing;
+
+    #[test]
+    fn build_applies_the_replicas_and_hasher() {
+        let ring = HashRing::builder()
+            .replicas(128)
+            .hasher(Identity::default())
+            .nodes([1_000u64, 5_000])
+            .build();
+
+        // Replica `r` of node `n` is at `n + r` with this hasher.
+        assert_eq!(ring.replicas(), 128);
+        assert_eq!(ring.iter().len(), 256);
+        assert_eq!(ring.min_position(), Some(1_000));
+        assert_eq!(ring.max_position(), Some(5_127));
+        assert_eq!(ring.get_node(&1_127u64), Some(&1_000));
+        assert_eq!(ring.get_node(&1_128u64), Some(&5_000));
+        assert_eq!(ring.get_node(&5_128u64), Some(&1_000));
+    }
+}
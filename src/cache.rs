@@ -0,0 +1,267 @@
+use alloc::boxed::Box;
+use core::{
+    fmt,
+    sync::atomic::{fence, AtomicU64, AtomicU8, Ordering},
+};
+
+use crate::Position;
+
+/// How many sets of two entries a ring's cache has, so it remembers the
+/// owners of up to twice this many key hashes.
+const SETS: usize = 128;
+
+/// The most recently looked up key hashes, each with the position of the
+/// point that owned it, so [`HashRing::get_node`](crate::HashRing::get_node)
+/// can skip the walk down the ring for hot keys.
+///
+/// Only available with the `cache` feature. Every entry is tagged with the
+/// generation it was found in, and the ring bumps the generation whenever
+/// a change could give a key another owner, which turns every entry stale
+/// at once without touching them. There is no lock: each entry is read and
+/// written under its own sequence number, and a lookup that catches an
+/// entry mid-write treats it as a miss.
+pub(crate) struct LookupCache {
+    /// Starts at one, so a zeroed entry is never current.
+    generation: AtomicU64,
+    /// A key hash can only go in the set its low bits pick, where it takes
+    /// the place of the entry used least recently.
+    sets: Box<[[Entry; 2]]>,
+    /// For each set, which of its entries was used least recently.
+    older: Box<[AtomicU8]>,
+}
+
+#[derive(Default)]
+struct Entry {
+    /// Odd while the entry is being written.
+    sequence: AtomicU64,
+    generation: AtomicU64,
+    hash: [AtomicU64; 2],
+    position: [AtomicU64; 2],
+}
+
+impl LookupCache {
+    /// Returns the current generation, to pass to [`get`](Self::get) and
+    /// [`insert`](Self::insert) around one lookup.
+    pub(crate) fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Turns every entry stale.
+    pub(crate) fn invalidate(&self) {
+        self.generation.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Returns the position of the point that owned `hash` in `generation`,
+    /// if it is remembered.
+    pub(crate) fn get<P: Position>(&self, hash: P, generation: u64) -> Option<P> {
+        let set = set_of(hash);
+        let hash = hash.to_u128();
+        for (way, entry) in self.sets[set].iter().enumerate() {
+            if let Some((found, position)) = entry.read(generation) {
+                if found == hash {
+                    self.older[set].store(1 - way as u8, Ordering::Relaxed);
+                    return Some(P::truncate_from(position));
+                }
+            }
+        }
+        None
+    }
+
+    /// Remembers that `hash` was owned by the point at `position` in
+    /// `generation`, forgetting the set's least recently used entry.
+    pub(crate) fn insert<P: Position>(&self, hash: P, position: P, generation: u64) {
+        let set = set_of(hash);
+        let hash = hash.to_u128();
+        let ways = &self.sets[set];
+        // Another thread may have remembered the same hash meanwhile, and
+        // an entry from an older generation is free.
+        let way = (0..2)
+            .find(|&way| {
+                ways[way]
+                    .read(generation)
+                    .is_none_or(|(found, _)| found == hash)
+            })
+            .unwrap_or(usize::from(self.older[set].load(Ordering::Relaxed)) & 1);
+        if ways[way].write(generation, hash, position.to_u128()) {
+            self.older[set].store(1 - way as u8, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Entry {
+    /// Returns the hash and position stored for `generation`, or `None` if
+    /// the entry is from another generation or is being written.
+    fn read(&self, generation: u64) -> Option<(u128, u128)> {
+        let sequence = self.sequence.load(Ordering::Acquire);
+        if sequence & 1 == 1 {
+            return None;
+        }
+        let stored = self.generation.load(Ordering::Relaxed);
+        let hash = join(&self.hash);
+        let position = join(&self.position);
+        fence(Ordering::Acquire);
+        if self.sequence.load(Ordering::Relaxed) != sequence || stored != generation {
+            return None;
+        }
+        Some((hash, position))
+    }
+
+    /// Stores `hash` and `position` for `generation`, returning `false`
+    /// without doing so if another thread is writing the entry.
+    fn write(&self, generation: u64, hash: u128, position: u128) -> bool {
+        let sequence = self.sequence.load(Ordering::Relaxed);
+        if sequence & 1 == 1
+            || self
+                .sequence
+                .compare_exchange(sequence, sequence + 1, Ordering::Acquire, Ordering::Relaxed)
+                .is_err()
+        {
+            return false;
+        }
+        fence(Ordering::Release);
+        self.generation.store(generation, Ordering::Relaxed);
+        split(&self.hash, hash);
+        split(&self.position, position);
+        self.sequence.store(sequence + 2, Ordering::Release);
+        true
+    }
+}
+
+/// Picks the set a key hash goes in by its low bits, which any hasher fit
+/// for a ring spreads well.
+fn set_of<P: Position>(hash: P) -> usize {
+    hash.to_u128() as usize % SETS
+}
+
+fn join(words: &[AtomicU64; 2]) -> u128 {
+    let low = words[0].load(Ordering::Relaxed);
+    let high = words[1].load(Ordering::Relaxed);
+    u128::from(low) | u128::from(high) << 64
+}
+
+fn split(words: &[AtomicU64; 2], value: u128) {
+    words[0].store(value as u64, Ordering::Relaxed);
+    words[1].store((value >> 64) as u64, Ordering::Relaxed);
+}
+
+impl Default for LookupCache {
+    fn default() -> Self {
+        LookupCache {
+            generation: AtomicU64::new(1),
+            sets: (0..SETS).map(|_| Default::default()).collect(),
+            older: (0..SETS).map(|_| AtomicU8::new(0)).collect(),
+        }
+    }
+}
+
+/// A clone starts out empty; it will fill up again with the clone's own
+/// hot keys.
+impl Clone for LookupCache {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl fmt::Debug for LookupCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LookupCache")
+            .field("generation", &self.generation())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{sample_keys, tests::Identity, HashRing, Xxh3Hash128Builder};
+
+    #[test]
+    fn repeated_lookups_are_answered_from_the_cache() {
+        let mut ring = HashRing::with_hasher(Identity::default());
+        ring.add_nodes([100u64, 200, 300]);
+        assert_eq!(ring.get_node(&150u64), Some(&200));
+        let generation = ring.cache.generation();
+        assert_eq!(ring.cache.get(150u64, generation), Some(200));
+
+        // A planted answer is returned as is, so the walk was skipped.
+        ring.cache.insert(150u64, 300u64, generation);
+        assert_eq!(ring.get_node(&150u64), Some(&300));
+        assert_eq!(ring.get_node_allow_draining(&150u64), Some(&200));
+        assert_eq!(ring.clone().get_node(&150u64), Some(&200));
+    }
+
+    #[test]
+    fn membership_changes_turn_cached_owners_stale() {
+        let mut ring = HashRing::with_hasher(Identity::default());
+        ring.add_nodes([100u64, 300]);
+        assert_eq!(ring.get_node(&250u64), Some(&300));
+
+        ring.add_node(260);
+        assert_eq!(ring.get_node(&250u64), Some(&260));
+        ring.remove_node(&260);
+        assert_eq!(ring.get_node(&250u64), Some(&300));
+
+        ring.drain_node(&300);
+        assert_eq!(ring.get_node(&250u64), Some(&100));
+        ring.remove_node(&300);
+        ring.set_weight(&100, 2);
+        ring.add_node(300);
+        assert_eq!(ring.get_node(&250u64), Some(&300));
+
+        ring.set_health(|node| *node != 300);
+        assert_eq!(ring.get_node(&250u64), Some(&100));
+        ring.clear_health();
+        assert_eq!(ring.get_node(&250u64), Some(&300));
+        ring.clear();
+        assert_eq!(ring.get_node(&250u64), None);
+    }
+
+    #[test]
+    fn cached_lookups_agree_with_the_ring_at_every_width() {
+        let mut ring = HashRing::with_replicas(16);
+        ring.add_nodes(0..40u64);
+        let mut wide = HashRing::with_replicas_and_hasher(16, Xxh3Hash128Builder::default());
+        wide.add_nodes(0..40u64);
+
+        // Far more keys than entries, looked up twice, so entries are
+        // evicted and hit alike.
+        let keys = sample_keys(79, 2_000);
+        for _ in 0..2 {
+            for key in &keys {
+                let hash = ring.hash_one(key);
+                assert_eq!(
+                    ring.get_node(key),
+                    ring.available_successor(hash, false).map(|(_, node)| node)
+                );
+                let hash = wide.hash_one(key);
+                assert_eq!(
+                    wide.get_node(key),
+                    wide.available_successor(hash, false).map(|(_, node)| node)
+                );
+            }
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn threads_share_a_ring_and_its_cache() {
+        let mut ring = HashRing::with_replicas(16);
+        ring.add_nodes(0..20u64);
+        let hot: alloc::vec::Vec<u64> = sample_keys(790, 64);
+        let expected: alloc::vec::Vec<_> = hot
+            .iter()
+            .map(|key| ring.owner_of_position(ring.hash_one(key)))
+            .collect();
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..500 {
+                        for (key, owner) in hot.iter().zip(&expected) {
+                            assert_eq!(ring.get_node(key), *owner);
+                        }
+                    }
+                });
+            }
+        });
+    }
+}
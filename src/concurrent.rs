@@ -0,0 +1,177 @@
+use core::hash::Hash;
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::{DefaultHashBuilder, HashRing, RingHasher};
+
+/// A [`HashRing`] behind an `RwLock`, for sharing one ring between many
+/// threads that route keys often and change membership rarely.
+///
+/// Lookups take the read lock, so any number of them run in parallel;
+/// membership changes take the write lock. A panic while the lock is held
+/// does not make the ring unusable: the lock's poison flag is ignored,
+/// since every operation on the ring leaves it consistent before it could
+/// panic.
+#[derive(Debug)]
+pub struct ConcurrentHashRing<T, S: RingHasher = DefaultHashBuilder> {
+    ring: RwLock<HashRing<T, S>>,
+}
+
+impl<T, S: RingHasher + Default> Default for ConcurrentHashRing<T, S> {
+    fn default() -> Self {
+        Self::from(HashRing::default())
+    }
+}
+
+impl<T, S: RingHasher> From<HashRing<T, S>> for ConcurrentHashRing<T, S> {
+    fn from(ring: HashRing<T, S>) -> Self {
+        ConcurrentHashRing {
+            ring: RwLock::new(ring),
+        }
+    }
+}
+
+impl<T, S: RingHasher> ConcurrentHashRing<T, S> {
+    /// Wraps `ring` for shared use.
+    pub fn new(ring: HashRing<T, S>) -> Self {
+        Self::from(ring)
+    }
+
+    /// Locks the ring for reading, for lookups not covered by the
+    /// convenience methods.
+    pub fn read(&self) -> RwLockReadGuard<'_, HashRing<T, S>> {
+        self.ring.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Locks the ring for writing, for changes not covered by the
+    /// convenience methods.
+    pub fn write(&self) -> RwLockWriteGuard<'_, HashRing<T, S>> {
+        self.ring.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Unwraps the ring.
+    pub fn into_inner(self) -> HashRing<T, S> {
+        self.ring
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T: Hash + Ord + Clone, S: RingHasher> ConcurrentHashRing<T, S> {
+    /// Returns a clone of the node that owns `key`.
+    ///
+    /// The node is cloned because the read lock is released before this
+    /// returns.
+    pub fn get_node<K: Hash + ?Sized>(&self, key: &K) -> Option<T> {
+        self.read().get_node_cloned(key)
+    }
+
+    /// Adds `node` to the ring, returning `false` if it was already there.
+    pub fn add_node(&self, node: T) -> bool {
+        self.write().add_node(node)
+    }
+
+    /// Removes `node` from the ring, returning the ring's copy of it if it
+    /// was there.
+    pub fn remove_node(&self, node: &T) -> Option<T> {
+        self.write().remove_node(node)
+    }
+
+    /// Adds and removes nodes as one change, under a single write lock, so
+    /// no reader sees some of it applied and the rest not.
+    ///
+    /// See [`HashRing::apply_changes`].
+    pub fn apply_changes(&self, additions: Vec<T>, removals: Vec<&T>)
+    where
+        S: Clone,
+    {
+        self.write().apply_changes(additions, removals);
+    }
+
+    /// Returns `true` if `node` is on the ring.
+    pub fn contains_node(&self, node: &T) -> bool {
+        self.read().contains_node(node)
+    }
+
+    /// Returns the number of nodes on the ring.
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    /// Returns `true` if the ring has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.read().is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn readers_and_writers_share_the_ring() {
+        let ring = ConcurrentHashRing::new(HashRing::with_replicas(16));
+        for node in 0..4u32 {
+            ring.add_node(node);
+        }
+
+        std::thread::scope(|scope| {
+            for reader in 0..8u64 {
+                let ring = &ring;
+                scope.spawn(move || {
+                    for key in 0..2_000u64 {
+                        // Nodes 0 to 3 are never removed, so every key has
+                        // an owner whatever the writers are doing.
+                        assert!(ring.get_node(&(reader, key)).is_some());
+                    }
+                });
+            }
+            for writer in 0..2u32 {
+                let ring = &ring;
+                scope.spawn(move || {
+                    for round in 0..200 {
+                        let node = 100 + writer * 1_000 + round;
+                        assert!(ring.add_node(node));
+                        assert_eq!(ring.remove_node(&node), Some(node));
+                    }
+                });
+            }
+        });
+
+        assert_eq!(ring.len(), 4);
+        assert!(ring.into_inner().check_invariants().is_ok());
+    }
+
+    #[test]
+    fn readers_see_a_change_applied_whole_or_not_at_all() {
+        let (first, second) = ([0u32, 1, 2], [10u32, 11, 12]);
+        let ring = ConcurrentHashRing::new(HashRing::with_replicas(8));
+        ring.apply_changes(first.to_vec(), Vec::new());
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                let ring = &ring;
+                scope.spawn(move || {
+                    for _ in 0..2_000 {
+                        let nodes: Vec<u32> = ring.read().nodes().copied().collect();
+                        assert!(nodes == first || nodes == second, "{nodes:?}");
+                    }
+                });
+            }
+            let ring = &ring;
+            scope.spawn(move || {
+                for round in 0..200 {
+                    let (from, to) = if round % 2 == 0 {
+                        (first, second)
+                    } else {
+                        (second, first)
+                    };
+                    ring.apply_changes(to.to_vec(), from.iter().collect());
+                }
+            });
+        });
+
+        let mut sequential = HashRing::with_replicas(8);
+        sequential.add_nodes(first);
+        assert_eq!(ring.into_inner(), sequential);
+    }
+}
@@ -0,0 +1,60 @@
+use core::fmt;
+
+/// The ways an operation on a [`HashRing`](crate::HashRing) can fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum HashRingError {
+    /// The ring has no nodes, so there is nothing to route a key to.
+    ///
+    /// Returned by [`try_get_node`](crate::HashRing::try_get_node) on a ring
+    /// that is empty, either because nothing has been added yet or because
+    /// every node has since been removed.
+    EmptyRing,
+
+    /// The bytes given to [`from_bytes`](crate::HashRing::from_bytes) are
+    /// not an encoding of a ring with the expected node and position types.
+    InvalidBytes,
+}
+
+impl fmt::Display for HashRingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HashRingError::EmptyRing => f.write_str("the hash ring has no nodes"),
+            HashRingError::InvalidBytes => f.write_str("the bytes do not encode a hash ring"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for HashRingError {}
+
+/// Why [`HashRing::try_from_nodes`](crate::HashRing::try_from_nodes)
+/// refused a list of nodes, naming the nodes involved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeConflict<T> {
+    /// The node appears in the list more than once.
+    Duplicate(T),
+
+    /// A point of `node` hashed to the same position as a point of `other`,
+    /// which came earlier in the list.
+    Collision {
+        /// The node whose point collided.
+        node: T,
+        /// The node already at the position.
+        other: T,
+    },
+}
+
+impl<T: fmt::Debug> fmt::Display for NodeConflict<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NodeConflict::Duplicate(node) => write!(f, "node {node:?} is listed twice"),
+            NodeConflict::Collision { node, other } => {
+                write!(f, "a point of node {node:?} collides with one of {other:?}")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: fmt::Debug> std::error::Error for NodeConflict<T> {}
@@ -0,0 +1,173 @@
+use core::hash::{BuildHasher, Hasher};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// A [`BuildHasher`] whose output is fixed for all time: the same input
+/// and seed hash the same today, next year, on another machine and under
+/// another Rust release.
+///
+/// This is the hasher a [`HashRing`](crate::HashRing) uses unless given
+/// another, so that its layout is reproducible. It needs no dependencies,
+/// as it is FNV-1a followed by the 64-bit finalizer from MurmurHash3, which
+/// spreads the small differences between replicas of one node over the
+/// whole ring. Its [`Hasher`] also writes integers as little-endian and
+/// `usize` and `isize` as 64 bits, so the byte order and pointer width of
+/// the machine do not leak into derived `Hash` impls. It is not resistant
+/// to deliberately colliding input.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FixedHasher {
+    seed: u64,
+}
+
+impl FixedHasher {
+    /// Creates a hasher with a zero seed, the same as [`Default`].
+    pub const fn new() -> Self {
+        Self::with_seed(0)
+    }
+
+    /// Creates a hasher with `seed`. Rings that hash with different seeds
+    /// lay out the same nodes independently.
+    pub const fn with_seed(seed: u64) -> Self {
+        FixedHasher { seed }
+    }
+}
+
+impl BuildHasher for FixedHasher {
+    type Hasher = Fnv1aHasher;
+
+    fn build_hasher(&self) -> Fnv1aHasher {
+        Fnv1aHasher {
+            state: FNV_OFFSET_BASIS ^ self.seed,
+        }
+    }
+}
+
+/// The [`Hasher`] built by [`FixedHasher`].
+#[derive(Debug, Clone)]
+pub struct Fnv1aHasher {
+    state: u64,
+}
+
+impl Hasher for Fnv1aHasher {
+    fn finish(&self) -> u64 {
+        let mut hash = self.state;
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+        hash ^ (hash >> 33)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.state = (self.state ^ u64::from(byte)).wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_i16(&mut self, i: i16) {
+        self.write_u16(i as u16);
+    }
+
+    fn write_i32(&mut self, i: i32) {
+        self.write_u32(i as u32);
+    }
+
+    fn write_i64(&mut self, i: i64) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_i128(&mut self, i: i128) {
+        self.write_u128(i as u128);
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_u64(i as i64 as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::HashRing;
+
+    #[test]
+    fn every_instance_hashes_alike() {
+        for value in ["", "key", "another key"] {
+            assert_eq!(
+                FixedHasher::new().hash_one(value),
+                FixedHasher::default().hash_one(value)
+            );
+            assert_eq!(
+                FixedHasher::with_seed(7).hash_one(value),
+                FixedHasher::with_seed(7).hash_one(value)
+            );
+            assert_ne!(
+                FixedHasher::new().hash_one(value),
+                FixedHasher::with_seed(7).hash_one(value)
+            );
+        }
+    }
+
+    /// Positions worked out apart from this crate, from FNV-1a and the
+    /// MurmurHash3 finalizer as published: a `str` hashes its bytes and a
+    /// `0xff`, a `u64` its eight little-endian bytes, and a node's replica
+    /// the node followed by the replica number as a `u64`.
+    #[test]
+    fn the_default_ring_places_keys_at_recorded_positions() {
+        let mut ring = HashRing::with_replicas(3);
+        ring.add_node("a");
+
+        for (key, position) in [
+            ("", 0x1bbd_5c81_3c69_a8d7),
+            ("key", 0xef15_5762_c4cb_2c57),
+            ("user:42", 0xb117_43b3_83b4_32c3),
+        ] {
+            assert_eq!(ring.hash_one(key), position, "{key:?}");
+        }
+        for (key, position) in [
+            (0u64, 0x7bd3_144f_29c0_cc9e),
+            (1, 0x4a3a_3a4b_a652_3826),
+            (42, 0xa624_5a5d_cf27_8758),
+            (u64::MAX, 0x6a92_c022_8678_c02e),
+        ] {
+            assert_eq!(ring.hash_one(&key), position, "{key}");
+        }
+        assert_eq!(
+            FixedHasher::with_seed(7).hash_one("key"),
+            0xc1a0_66ce_f585_bf50
+        );
+
+        let points: Vec<u64> = ring.iter().map(|(&position, _)| position).collect();
+        assert_eq!(
+            points,
+            [
+                0x7c9f_30c0_f819_df70,
+                0xd656_ce46_917c_b6d1,
+                0xed8d_f223_76de_5175
+            ]
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use alloc::vec::Vec;
+use core::{hash::Hash, ops::Range};
+
+use crate::{salted_position, DefaultHashBuilder, HashRing, Position, RingHasher};
+
+/// A ring whose membership can no longer change, laid out for lookups.
+///
+/// Made by [`HashRing::freeze`]. The points are kept in one sorted `Vec`
+/// searched by bisection, which touches far less memory per lookup than
+/// walking a B-tree, and there is nothing to mutate. A key goes to the same
+/// node as on the ring it was frozen from, without the health check, which
+/// a frozen ring does not keep.
+#[derive(Debug, Clone)]
+pub struct FrozenRing<T, S: RingHasher = DefaultHashBuilder> {
+    points: Vec<(S::Position, T)>,
+    hasher: S,
+    salt: u64,
+    /// For each value of a hash's top `jump_bits` bits, the index of the
+    /// first point at or above the lowest such hash, and then `points.len()`.
+    /// Empty when there is no jump table.
+    jump: Vec<usize>,
+    jump_bits: u32,
+}
+
+impl<T, S: RingHasher> HashRing<T, S> {
+    /// Turns the ring into a [`FrozenRing`], for when its membership is
+    /// settled and only lookups remain.
+    pub fn freeze(self) -> FrozenRing<T, S> {
+        FrozenRing {
+            points: self.keys.into_iter().collect(),
+            hasher: self.hasher,
+            salt: self.salt,
+            jump: Vec::new(),
+            jump_bits: 0,
+        }
+    }
+}
+
+impl<T, S: RingHasher> FrozenRing<T, S> {
+    /// Adds a table of `2^bits` entries that takes a lookup straight to the
+    /// points sharing its hash's top `bits` bits, so only those are
+    /// searched.
+    ///
+    /// With about as many entries as points, most lookups bisect over a
+    /// point or two instead of the whole ring, for the hottest lookup paths.
+    /// A key still goes to the same node. The table costs a `usize` per
+    /// entry. `0` bits removes it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bits` is more than 24.
+    pub fn with_jump_table(mut self, bits: u32) -> Self {
+        assert!(
+            bits <= 24.min(S::Position::BITS),
+            "a jump table can have at most 24 bits"
+        );
+        self.jump_bits = bits;
+        self.jump.clear();
+        if bits == 0 {
+            return self;
+        }
+
+        let shift = S::Position::BITS - bits;
+        let mut index = 0;
+        for bucket in 0..1usize << bits {
+            while index < self.points.len()
+                && self.points[index].0.to_u128() >> shift < bucket as u128
+            {
+                index += 1;
+            }
+            self.jump.push(index);
+        }
+        self.jump.push(self.points.len());
+        self
+    }
+
+    /// Returns the node that owns `key`, or `None` if the ring was frozen
+    /// with no nodes.
+    pub fn get_node<K: Hash + ?Sized>(&self, key: &K) -> Option<&T> {
+        let hash_key = salted_position(&self.hasher, self.salt, key);
+        let window = self.search_window(hash_key);
+        let index = window.start
+            + self.points[window].partition_point(|&(position, _)| position < hash_key);
+        self.points
+            .get(index)
+            .or_else(|| self.points.first())
+            .map(|(_, node)| node)
+    }
+
+    /// Returns the range of points the successor of `hash` has to be
+    /// searched for in: every point before it is below `hash`, and every
+    /// point from its end on is above it. That is all of them without a jump
+    /// table.
+    fn search_window(&self, hash: S::Position) -> Range<usize> {
+        if self.jump.is_empty() {
+            return 0..self.points.len();
+        }
+        let bucket = (hash.to_u128() >> (S::Position::BITS - self.jump_bits)) as usize;
+        self.jump[bucket]..self.jump[bucket + 1]
+    }
+
+    /// Returns an iterator over every point as `(position, node)`, in
+    /// ascending order of position.
+    pub fn iter(&self) -> impl Iterator<Item = (S::Position, &T)> {
+        self.points.iter().map(|(position, node)| (*position, node))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sample_keys;
+
+    #[test]
+    fn a_frozen_ring_routes_as_its_source_did() {
+        let mut ring: HashRing<u64> = HashRing::builder().replicas(16).salt(91).build();
+        ring.add_nodes(sample_keys(1, 200));
+        ring.add_weighted_node(7, 3);
+        let frozen = ring.clone().freeze();
+
+        assert!(frozen.iter().eq(ring.iter().map(|(&at, node)| (at, node))));
+        for key in sample_keys(91, 5_000) {
+            assert_eq!(frozen.get_node(&key), ring.get_node(&key));
+        }
+        assert_eq!(HashRing::<u64>::new().freeze().get_node(&1u64), None);
+    }
+
+    #[test]
+    fn a_jump_table_routes_alike_with_fewer_comparisons() {
+        let mut ring: HashRing<u64> = HashRing::with_replicas(16);
+        ring.add_nodes(sample_keys(1, 1_000));
+        let plain = ring.freeze();
+        let points = plain.iter().count();
+        let jumped = plain.clone().with_jump_table(14);
+
+        let keys = sample_keys(110, 5_000);
+        let mut searched = 0;
+        for key in &keys {
+            assert_eq!(jumped.get_node(key), plain.get_node(key));
+            let hash = salted_position(&jumped.hasher, jumped.salt, key);
+            assert_eq!(plain.search_window(hash).len(), points);
+            searched += jumped.search_window(hash).len();
+        }
+
+        // Bisecting a window of `n` points takes about `log2(n) + 1`
+        // comparisons, so a window of a couple of points instead of 16,000
+        // is around two comparisons instead of fifteen.
+        let mean_window = searched as f64 / keys.len() as f64;
+        assert!(mean_window < 4.0, "{mean_window}");
+        assert_eq!(plain.with_jump_table(0).search_window(0).len(), points);
+    }
+}
@@ -0,0 +1,114 @@
+use core::hash::{BuildHasher, Hash};
+
+use crate::DefaultHashBuilder;
+
+/// Maps `key` to one of `num_buckets` buckets with Lamping and Veach's
+/// jump consistent hash.
+///
+/// Keys spread evenly over the buckets, and growing from `n` to `n + 1`
+/// buckets moves only the `1 / (n + 1)` of keys that land in the new bucket.
+/// Buckets can only be added or removed at the end of the range, so this
+/// suits numbered shards rather than named nodes that come and go.
+///
+/// # Panics
+///
+/// Panics if `num_buckets` is zero.
+pub fn jump_hash(mut key: u64, num_buckets: u32) -> u32 {
+    assert!(num_buckets > 0, "jump hash needs at least one bucket");
+
+    let mut bucket: i64 = -1;
+    let mut next: i64 = 0;
+    while next < i64::from(num_buckets) {
+        bucket = next;
+        key = key.wrapping_mul(2_862_933_555_777_941_757).wrapping_add(1);
+        next = ((bucket + 1) as f64 * ((1u64 << 31) as f64 / ((key >> 33) + 1) as f64)) as i64;
+    }
+    bucket as u32
+}
+
+/// Routes keys to numbered buckets `0..buckets` with [`jump_hash`].
+///
+/// Unlike [`HashRing`](crate::HashRing) this keeps no per-node state at all,
+/// so it costs the same to route over a million buckets as over two.
+#[derive(Debug, Clone, Default)]
+pub struct JumpHashRing<S = DefaultHashBuilder> {
+    buckets: u32,
+    hasher: S,
+}
+
+impl JumpHashRing {
+    /// Creates a ring of `buckets` buckets.
+    pub fn new(buckets: u32) -> Self {
+        Self::with_hasher(buckets, DefaultHashBuilder::default())
+    }
+}
+
+impl<S: BuildHasher> JumpHashRing<S> {
+    /// Creates a ring of `buckets` buckets that hashes keys with `hasher`
+    /// before jumping.
+    pub fn with_hasher(buckets: u32, hasher: S) -> Self {
+        JumpHashRing { buckets, hasher }
+    }
+
+    /// Returns the number of buckets.
+    pub fn len(&self) -> u32 {
+        self.buckets
+    }
+
+    /// Returns `true` if there are no buckets.
+    pub fn is_empty(&self) -> bool {
+        self.buckets == 0
+    }
+
+    /// Adds a bucket at the end of the range and returns its index.
+    pub fn add_bucket(&mut self) -> u32 {
+        self.buckets += 1;
+        self.buckets - 1
+    }
+
+    /// Removes the last bucket and returns its index, or `None` if there
+    /// were no buckets.
+    pub fn remove_bucket(&mut self) -> Option<u32> {
+        self.buckets = self.buckets.checked_sub(1)?;
+        Some(self.buckets)
+    }
+
+    /// Returns the bucket that owns `key`, or `None` if there are no
+    /// buckets.
+    pub fn get_bucket<K: Hash + ?Sized>(&self, key: &K) -> Option<u32> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(jump_hash(self.hasher.hash_one(key), self.buckets))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jump_hash_matches_the_reference_vectors() {
+        // From the test suite of the Go port of the paper's reference code.
+        assert_eq!(jump_hash(1, 1), 0);
+        assert_eq!(jump_hash(42, 57), 43);
+        assert_eq!(jump_hash(0xdead_10cc, 1), 0);
+        assert_eq!(jump_hash(0xdead_10cc, 666), 361);
+        assert_eq!(jump_hash(256, 1024), 520);
+    }
+
+    #[test]
+    fn jump_hash_spreads_keys_evenly() {
+        let ring = JumpHashRing::new(10);
+        let keys = crate::sample_keys(33, 100_000);
+        let mut counts = [0usize; 10];
+        for key in &keys {
+            counts[ring.get_bucket(key).unwrap() as usize] += 1;
+        }
+        for count in counts {
+            let share = count as f64 / keys.len() as f64;
+            assert!((0.095..0.105).contains(&share), "a bucket got {share}");
+        }
+        assert_eq!(JumpHashRing::new(0).get_bucket(&1u64), None);
+    }
+}
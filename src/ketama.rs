@@ -0,0 +1,187 @@
+use alloc::{collections::BTreeMap, format};
+use core::fmt::Display;
+
+use crate::md5::md5;
+
+/// A ring laid out exactly the way libketama lays out its continuum, so a
+/// Rust service can route keys to the same memcached servers as the
+/// clients it shares them with.
+///
+/// Nodes are named by their `Display` output, which should be the server's
+/// `"host:port"` string as it appears in the ketama server list. Each node
+/// gets `40 * weight / mean weight` MD5 digests of `"<node>-<n>"` for `n`
+/// counting from zero, rounded down, and each digest gives four points, so
+/// nodes of equal weight have 160 points each. Keys hash to the first four
+/// bytes of their MD5 digest, read little-endian, and belong to the first
+/// point at or after that hash, wrapping around.
+///
+/// Because every node's point count depends on the total weight, the ring
+/// is laid out again from scratch on each membership change. Where two
+/// points land on the same position the smallest node by `Ord` keeps it;
+/// libketama leaves that to the order its sort happens to produce.
+#[derive(Debug, Clone)]
+pub struct KetamaRing<T> {
+    nodes: BTreeMap<T, u32>,
+    continuum: BTreeMap<u32, T>,
+}
+
+impl<T> Default for KetamaRing<T> {
+    fn default() -> Self {
+        KetamaRing {
+            nodes: BTreeMap::new(),
+            continuum: BTreeMap::new(),
+        }
+    }
+}
+
+impl<T: Display + Ord + Clone> FromIterator<T> for KetamaRing<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut ring = KetamaRing {
+            nodes: iter.into_iter().map(|node| (node, 1)).collect(),
+            continuum: BTreeMap::new(),
+        };
+        ring.rebuild();
+        ring
+    }
+}
+
+/// Hashes `key` the way libketama does for lookups: the first four bytes of
+/// its MD5 digest, little-endian.
+pub fn ketama_hash(key: &[u8]) -> u32 {
+    let digest = md5(key);
+    u32::from_le_bytes([digest[0], digest[1], digest[2], digest[3]])
+}
+
+impl<T: Display + Ord + Clone> KetamaRing<T> {
+    /// Creates an empty ring.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of nodes.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns `true` if there are no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Adds `node` with a weight of one.
+    pub fn add_node(&mut self, node: T) {
+        self.add_weighted_node(node, 1);
+    }
+
+    /// Adds `node` with `weight`, the figure libketama reads from the
+    /// memory column of its server list.
+    ///
+    /// Adding a node that is already on the ring replaces its weight.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `weight` is zero.
+    pub fn add_weighted_node(&mut self, node: T, weight: u32) {
+        assert!(weight > 0, "a node needs a weight of at least one");
+        self.nodes.insert(node, weight);
+        self.rebuild();
+    }
+
+    /// Removes `node`.
+    pub fn remove_node(&mut self, node: &T) {
+        if self.nodes.remove(node).is_some() {
+            self.rebuild();
+        }
+    }
+
+    /// Returns `true` if `node` has been added.
+    pub fn contains_node(&self, node: &T) -> bool {
+        self.nodes.contains_key(node)
+    }
+
+    /// Returns the node that owns `key`, or `None` if there are no nodes.
+    pub fn get_node<K: AsRef<[u8]> + ?Sized>(&self, key: &K) -> Option<&T> {
+        let hash = ketama_hash(key.as_ref());
+        self.continuum
+            .range(hash..)
+            .next()
+            .or_else(|| self.continuum.first_key_value())
+            .map(|(_, node)| node)
+    }
+
+    /// Returns an iterator over every point of the continuum as
+    /// `(position, node)`, in ascending order of position.
+    pub fn points(&self) -> impl Iterator<Item = (u32, &T)> {
+        self.continuum
+            .iter()
+            .map(|(&position, node)| (position, node))
+    }
+
+    /// Returns an iterator over the nodes, in ascending order.
+    pub fn nodes(&self) -> impl Iterator<Item = &T> {
+        self.nodes.keys()
+    }
+
+    fn rebuild(&mut self) {
+        self.continuum.clear();
+        let total_weight: u64 = self.nodes.values().map(|&weight| u64::from(weight)).sum();
+        let node_count = self.nodes.len() as f64;
+
+        // Nodes are visited in ascending order, so the first to claim a
+        // position is the smallest one to collide there.
+        for (node, &weight) in &self.nodes {
+            // libketama takes the share as a float, multiplies it up in
+            // double precision and rounds down with `floorf`, which narrows
+            // the product back to a float first. Doing the same keeps the
+            // point counts identical when the share is inexact.
+            let share = weight as f32 / total_weight as f32;
+            let digests = (f64::from(share) * 40.0 * node_count) as f32 as u32;
+            for n in 0..digests {
+                let digest = md5(format!("{node}-{n}").as_bytes());
+                for chunk in digest.chunks_exact(4) {
+                    let position = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+                    self.continuum
+                        .entry(position)
+                        .or_insert_with(|| node.clone());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn points_and_lookups_match_libketama() {
+        let ring: KetamaRing<&str> = ["10.0.1.1:11211", "10.0.1.2:11211"].into_iter().collect();
+
+        assert_eq!(ring.points().count(), 320);
+        for (node, first_digest) in [
+            (
+                "10.0.1.1:11211",
+                [0x90ed8713, 0xf5ce3b03, 0x060386a6, 0xa22b367d],
+            ),
+            (
+                "10.0.1.2:11211",
+                [0x4edc625a, 0x62f5bf62, 0x770f04a9, 0xb1031030],
+            ),
+        ] {
+            assert_eq!(
+                ring.points().filter(|&(_, owner)| *owner == node).count(),
+                160
+            );
+            for position in first_digest {
+                assert!(ring.points().any(|point| point == (position, &node)));
+            }
+        }
+
+        assert_eq!(ketama_hash(b"foo"), 0xdb18bdac);
+        assert_eq!(ring.get_node("foo"), Some(&"10.0.1.2:11211"));
+        assert_eq!(ring.get_node("bar"), Some(&"10.0.1.1:11211"));
+        assert_eq!(ring.get_node("user:1234"), Some(&"10.0.1.1:11211"));
+        assert_eq!(ring.get_node("memcached"), Some(&"10.0.1.2:11211"));
+        assert_eq!(ring.points().next().map(|(at, _)| at), Some(0x0049a61e));
+    }
+}
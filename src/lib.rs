@@ -0,0 +1,4246 @@
+//! A consistent hash ring that maps keys onto a changing set of nodes.
+//!
+//! The crate is `no_std` compatible: disabling the default `std` feature
+//! leaves everything built on `alloc` alone.
+//!
+//! The `metrics` feature makes every ring count the lookups it answers, per
+//! node, for `HashRing::stats` and `HashRing::prometheus_text`.
+//!
+//! The `cache` feature makes every ring remember the owners of the keys it
+//! was most recently asked about, so repeated lookups of hot keys skip the
+//! walk down the ring.
+//!
+//! The `serde` feature implements `Serialize` and `Deserialize` for
+//! `HashRing`, writing out its settings and points so a ring can be
+//! restored exactly after a restart.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod balance;
+mod builder;
+#[cfg(feature = "cache")]
+mod cache;
+#[cfg(feature = "std")]
+mod concurrent;
+mod crc32;
+mod error;
+mod fixed;
+mod frozen;
+mod jump;
+mod ketama;
+mod md5;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod nginx;
+mod overlay;
+mod position;
+mod rendezvous;
+mod sample;
+#[cfg(feature = "serde")]
+mod serialize;
+mod strategy;
+mod tiered;
+mod weighted;
+mod wire;
+mod zoned;
+
+pub use balance::recommended_replicas;
+pub use builder::HashRingBuilder;
+#[cfg(feature = "std")]
+pub use concurrent::ConcurrentHashRing;
+pub use error::{HashRingError, NodeConflict};
+pub use fixed::{FixedHasher, Fnv1aHasher};
+pub use frozen::FrozenRing;
+pub use jump::{jump_hash, JumpHashRing};
+pub use ketama::{ketama_hash, KetamaRing};
+#[cfg(feature = "metrics")]
+pub use metrics::RingStats;
+pub use nginx::{nginx_hash, NginxRing};
+pub use overlay::{OverlayRing, RouteDecision};
+pub use position::{collision_probability, Position, RingHasher, Xxh3Hash128Builder};
+pub use rendezvous::RendezvousRing;
+pub use sample::sample_keys;
+pub use strategy::ConsistentHash;
+pub use tiered::TieredRing;
+pub use weighted::Weighted;
+pub use wire::WireNode;
+pub use zoned::Zoned;
+
+use alloc::{
+    collections::{btree_map, BTreeMap, BTreeSet},
+    format,
+    string::String,
+    sync::Arc,
+    vec,
+    vec::Vec,
+};
+use core::{
+    cmp::Ordering,
+    fmt::{self, Write},
+    hash::{BuildHasher, Hash, Hasher},
+    ops::{Index, RangeInclusive},
+};
+
+use position::arc_len;
+
+/// Hashes `value` with `hasher`, preceded by `salt` unless it is zero.
+pub(crate) fn salted_position<S: RingHasher, H: Hash + ?Sized>(
+    hasher: &S,
+    salt: u64,
+    value: &H,
+) -> S::Position {
+    if salt == 0 {
+        hasher.hash_position(value)
+    } else {
+        hasher.hash_position(&(salt, value))
+    }
+}
+
+/// How many distinct nodes clockwise from a key
+/// [`HashRing::get_node_biased`] and [`HashRing::get_node_dynamic`] choose
+/// between.
+const CANDIDATE_NODES: usize = 3;
+
+/// The hasher a [`HashRing`] uses when none is given.
+///
+/// This is [`FixedHasher`], whose output is fixed for all time, so a ring
+/// that routes a key to a node today does so after a toolchain upgrade and
+/// on every other machine too. With the `std` feature,
+/// `HashRing::with_default_hasher` opts into `StdHashBuilder` instead.
+pub type DefaultHashBuilder = FixedHasher;
+
+/// `std`'s `DefaultHasher` with its fixed initial keys, for
+/// [`HashRing::with_default_hasher`].
+///
+/// Placement with it is reproducible within a build, but the algorithm is
+/// not specified and may change in any Rust release. A ring whose nodes
+/// hold data by the keys routed to them then remaps nearly every key after
+/// upgrading the toolchain, with nothing to say so, and a ring shared
+/// between processes built by different releases disagrees with itself.
+#[cfg(feature = "std")]
+pub type StdHashBuilder = core::hash::BuildHasherDefault<std::collections::hash_map::DefaultHasher>;
+
+/// A ring of nodes of type `T` that routes keys to them.
+///
+/// Nodes and keys are hashed onto the same ring with `S`, and a key belongs
+/// to the first node found at or clockwise after its hash. The ring is
+/// 64 bits around for any [`BuildHasher`]; a
+/// [`RingHasher`] such as [`Xxh3Hash128Builder`] can pick another width.
+///
+/// When points of two different nodes hash to the same position, both are
+/// kept. The smallest node by `Ord` owns the position, and the others wait
+/// behind it to take over if it is removed, so which node wins never depends
+/// on the order the nodes were added in.
+#[derive(Debug, Clone)]
+pub struct HashRing<T, S: RingHasher = DefaultHashBuilder> {
+    keys: BTreeMap<S::Position, T>,
+    /// Nodes whose points collided with the owner of a position in `keys`,
+    /// sorted, and never including that owner.
+    collisions: BTreeMap<S::Position, Vec<T>>,
+    /// Every node on the ring, with the positions of all of its points so
+    /// removal never has to recompute them.
+    nodes: BTreeMap<T, NodeEntry<S::Position>>,
+    loads: BTreeMap<T, u64>,
+    replicas: usize,
+    hasher: S,
+    /// Mixed into everything hashed so rings with the same nodes can be
+    /// laid out independently; zero leaves hashing as it is.
+    salt: u64,
+    /// Whether points are spaced by double hashing rather than each hashed
+    /// on its own, set by the builder.
+    double_hashing: bool,
+    replica_key: Option<ReplicaKey<T>>,
+    health: Option<HealthCheck<T>>,
+    /// Nodes put out of reach of new lookups by `drain_node`.
+    draining: BTreeSet<T>,
+    #[cfg(feature = "metrics")]
+    lookups: metrics::Counter,
+    #[cfg(feature = "cache")]
+    cache: cache::LookupCache,
+}
+
+#[derive(Debug, Clone)]
+struct NodeEntry<P> {
+    weight: u32,
+    positions: Vec<P>,
+    #[cfg(feature = "metrics")]
+    hits: metrics::Counter,
+}
+
+/// Builds a ring with one point per node from `value`, as if by
+/// [`add_nodes`](HashRing::add_nodes).
+///
+/// Every distinct node in `value` ends up on the ring, so
+/// [`len`](HashRing::len) is the number of distinct nodes even where their
+/// points collide: the colliding nodes wait behind the owner of the
+/// position, as described on [`HashRing`].
+impl<T: Hash + Ord + Clone, S: RingHasher + Default> From<Vec<T>> for HashRing<T, S> {
+    fn from(value: Vec<T>) -> Self {
+        let mut hash_ring = HashRing::default();
+        hash_ring.add_nodes(value);
+        hash_ring
+    }
+}
+
+impl<T: Hash + Ord + Clone, S: RingHasher + Default> FromIterator<T> for HashRing<T, S> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut hash_ring = HashRing::default();
+        hash_ring.add_nodes(iter);
+        hash_ring
+    }
+}
+
+impl<T: Hash + Ord + Clone, S: RingHasher> Extend<T> for HashRing<T, S> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.add_nodes(iter);
+    }
+}
+
+impl<T, S: RingHasher + Default> Default for HashRing<T, S> {
+    fn default() -> Self {
+        HashRing {
+            keys: Default::default(),
+            collisions: Default::default(),
+            nodes: Default::default(),
+            loads: Default::default(),
+            replicas: 1,
+            hasher: S::default(),
+            salt: 0,
+            double_hashing: false,
+            replica_key: None,
+            health: None,
+            draining: BTreeSet::new(),
+            #[cfg(feature = "metrics")]
+            lookups: Default::default(),
+            #[cfg(feature = "cache")]
+            cache: Default::default(),
+        }
+    }
+}
+
+/// Two rings are equal when they have the same nodes at the same positions,
+/// regardless of how they were built or which hasher instance they hold.
+impl<T: PartialEq, S: RingHasher> PartialEq for HashRing<T, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.keys == other.keys && self.collisions == other.collisions
+    }
+}
+
+impl<T: Eq, S: RingHasher> Eq for HashRing<T, S> {}
+
+impl<'a, T, S: RingHasher> IntoIterator for &'a HashRing<T, S> {
+    type Item = (&'a S::Position, &'a T);
+    type IntoIter = Iter<'a, T, S::Position>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Looks up the node that owns a key, like
+/// [`get_node`](HashRing::get_node).
+///
+/// # Panics
+///
+/// Panics if the ring is empty.
+impl<K: Hash + ?Sized, T: Hash + Ord + Clone, S: RingHasher> Index<&K> for HashRing<T, S> {
+    type Output = T;
+
+    fn index(&self, key: &K) -> &T {
+        self.get_node(key)
+            .expect("cannot look up a key on a hash ring with no nodes")
+    }
+}
+
+/// Summarizes the ring on one line for logging, as in
+/// `HashRing(3 nodes, 384 points): a, b, c`, with the nodes in ascending
+/// order. [`ascii_ring`](HashRing::ascii_ring) draws where the points are.
+impl<T: fmt::Display, S: RingHasher> fmt::Display for HashRing<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let points: usize = self.nodes.values().map(|entry| entry.positions.len()).sum();
+        let plural = |count: usize| if count == 1 { "" } else { "s" };
+        write!(
+            f,
+            "HashRing({} node{}, {} point{})",
+            self.nodes.len(),
+            plural(self.nodes.len()),
+            points,
+            plural(points),
+        )?;
+        for (index, node) in self.nodes.keys().enumerate() {
+            f.write_str(if index == 0 { ": " } else { ", " })?;
+            write!(f, "{node}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Hash + Ord + Clone> HashRing<T> {
+    /// Creates an empty ring with one point per node and the default
+    /// hasher.
+    ///
+    /// Keys are chosen per lookup, so nothing about them needs naming here:
+    /// `HashRing::<Port>::new()` can go on to route both `&str` and `u64`
+    /// keys.
+    pub fn new() -> Self {
+        Self::with_replicas(1)
+    }
+
+    /// Returns a [`HashRingBuilder`] for configuring a ring's replicas,
+    /// hasher and initial nodes in one expression.
+    pub fn builder() -> HashRingBuilder<T> {
+        HashRingBuilder::new()
+    }
+
+    /// Creates an empty ring that places `replicas` virtual points on the
+    /// ring for every node added to it.
+    ///
+    /// With a single point per node the arcs between nodes vary wildly in
+    /// size, so a handful of nodes can end up owning most of the keyspace.
+    /// Spreading each node over many points evens this out: the share of
+    /// keys each node receives converges on `1 / nodes` as `replicas` grows,
+    /// with a few hundred replicas keeping every node within a few percent.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `replicas` is zero.
+    pub fn with_replicas(replicas: usize) -> Self {
+        Self::with_replicas_and_hasher(replicas, DefaultHashBuilder::default())
+    }
+
+    /// Creates an empty ring with `replicas` points per node, for about
+    /// `nodes` nodes.
+    ///
+    /// A ring of `nodes` unweighted nodes holds `nodes * replicas` points,
+    /// and a node of weight `w` adds `w * replicas` of them. The points and
+    /// the nodes live in B-trees, which allocate node by node as they grow
+    /// and have no capacity to reserve. What can be reserved is each node's
+    /// list of positions, which is allocated for all of its points before
+    /// they are hashed, so adding a node never reallocates it; that holds
+    /// whichever constructor made the ring, and `nodes` only states the
+    /// size expected.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `replicas` is zero.
+    pub fn with_capacity(nodes: usize, replicas: usize) -> Self {
+        let _ = nodes;
+        Self::with_replicas(replicas)
+    }
+
+    /// Creates an empty ring with one point per node whose hashing is
+    /// salted with `salt`.
+    ///
+    /// Rings that hold the same nodes but have different salts place nodes
+    /// and keys independently of each other, so a node that one ring gives
+    /// a large share of the keys to is no more likely to get a large share
+    /// on another. That suits running a ring per kind of data over one set
+    /// of machines. A salt of zero is the same as no salt. The
+    /// [builder](HashRingBuilder::salt) sets one alongside other options.
+    pub fn with_salt(salt: u64) -> Self {
+        let mut hash_ring = Self::new();
+        hash_ring.salt = salt;
+        hash_ring
+    }
+
+    /// Creates a ring with `replicas` points per unit of weight and places
+    /// every `(node, weight)` pair in `topology` on it.
+    ///
+    /// Fed the output of [`topology`](Self::topology) and the replica count
+    /// of the ring it came from, this rebuilds a ring that routes every key
+    /// the same way, without having to store the ring's points, provided
+    /// that ring was laid out with none of the builder's placement settings:
+    /// the ring built here has no [salt](HashRingBuilder::salt), no
+    /// [double hashing](HashRingBuilder::double_hashing) and no
+    /// [`replica_key`](HashRingBuilder::replica_key). A ring that had any of
+    /// them has to be rebuilt through the [builder](Self::builder) with the
+    /// same settings, adding each pair with
+    /// [`weighted_node`](HashRingBuilder::weighted_node).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `replicas` or any weight is zero.
+    pub fn from_topology<I: IntoIterator<Item = (T, u32)>>(topology: I, replicas: usize) -> Self {
+        let mut hash_ring = Self::with_replicas(replicas);
+        for (node, weight) in topology {
+            hash_ring.add_weighted_node(node, weight);
+        }
+        hash_ring
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: Hash + Ord + Clone> HashRing<T, StdHashBuilder> {
+    /// Creates an empty ring with one point per node that hashes with
+    /// `std`'s `DefaultHasher` rather than the default [`FixedHasher`].
+    ///
+    /// Only use this for rings whose layout may change from one build to
+    /// the next: `DefaultHasher` can hash differently after any toolchain
+    /// upgrade, as described on [`StdHashBuilder`], moving nearly every key
+    /// to another node.
+    pub fn with_default_hasher() -> Self {
+        Self::with_hasher(StdHashBuilder::default())
+    }
+}
+
+impl<T: Hash + Ord + Clone, S: RingHasher> HashRing<T, S> {
+    /// Creates an empty ring that hashes nodes and keys with `hasher`.
+    ///
+    /// Two rings built with equivalent hashers and the same nodes route every
+    /// key identically, which makes a hasher with a fixed algorithm and seed,
+    /// such as the default [`FixedHasher`], the way to get placement that is
+    /// stable across processes.
+    pub fn with_hasher(hasher: S) -> Self {
+        Self::with_replicas_and_hasher(1, hasher)
+    }
+
+    /// Creates an empty ring with `replicas` points per node that hashes with
+    /// `hasher`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `replicas` is zero.
+    pub fn with_replicas_and_hasher(replicas: usize, hasher: S) -> Self {
+        assert!(
+            replicas > 0,
+            "a hash ring needs at least one replica per node"
+        );
+        HashRing {
+            keys: Default::default(),
+            collisions: Default::default(),
+            nodes: Default::default(),
+            loads: Default::default(),
+            replicas,
+            hasher,
+            salt: 0,
+            double_hashing: false,
+            replica_key: None,
+            health: None,
+            draining: BTreeSet::new(),
+            #[cfg(feature = "metrics")]
+            lookups: Default::default(),
+            #[cfg(feature = "cache")]
+            cache: Default::default(),
+        }
+    }
+
+    /// Hashes `value` to a position on the ring.
+    ///
+    /// This is the only place the ring hashes anything: node points, keys
+    /// and raw bytes all go through it. Each call starts from a fresh hasher
+    /// built by the ring's hasher, so the result depends only on `value`,
+    /// that hasher's algorithm and seed and the ring's
+    /// [`salt`](Self::salt), never on what was hashed before.
+    pub fn hash_one<H: Hash + ?Sized>(&self, value: &H) -> S::Position {
+        salted_position(&self.hasher, self.salt, value)
+    }
+
+    /// Returns the salt mixed into the ring's hashing, zero if there is
+    /// none.
+    pub fn salt(&self) -> u64 {
+        self.salt
+    }
+
+    /// Returns the number of nodes on the ring.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns `true` if no nodes have been added to the ring.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Returns every node on the ring with its weight, in ascending order
+    /// of node.
+    ///
+    /// Nodes added without a weight have a weight of `1`. Together with
+    /// [`replicas`](Self::replicas) this is all it takes to rebuild the ring
+    /// with [`HashRing::from_topology`], unless it was built with a
+    /// [`salt`](Self::salt), [double hashing](HashRingBuilder::double_hashing)
+    /// or another of the builder's placement settings, which the topology
+    /// leaves out.
+    pub fn topology(&self) -> Vec<(T, u32)> {
+        self.nodes
+            .iter()
+            .map(|(node, entry)| (node.clone(), entry.weight))
+            .collect()
+    }
+
+    /// Adds `node` to the ring, placing one point for each replica.
+    ///
+    /// Where a point goes depends only on the node, the replica's number
+    /// and the ring's hasher and salt, so adding a node never moves another
+    /// node's points: the only keys that change hands are those the new
+    /// points take. A point that lands on another node's position is kept
+    /// alongside it, as described on [`HashRing`].
+    ///
+    /// Returns `false`, leaving the ring as it is, if `node` is already on
+    /// it: its points would only be hashed to where they are. Use
+    /// [`add_weighted_node`](Self::add_weighted_node) to place a node that
+    /// is there anew.
+    pub fn add_node(&mut self, node: T) -> bool {
+        if self.contains_node(&node) {
+            return false;
+        }
+        self.add_weighted_node(node, 1);
+        true
+    }
+
+    /// Adds `node` to the ring like [`add_node`](Self::add_node), and
+    /// reports whether it went in cleanly.
+    ///
+    /// Returns `true` if every point was placed at a free position. Returns
+    /// `false` if one of its points collided with a position another node
+    /// already held, or if `node` was already on the ring, in which case
+    /// nothing changes: its points and weight stay as they were.
+    pub fn insert_node(&mut self, node: T) -> bool {
+        if self.contains_node(&node) {
+            return false;
+        }
+        self.insert_weighted_node(node, 1)
+    }
+
+    /// Adds every node in `nodes` to the ring, as if by calling
+    /// [`add_node`](Self::add_node) on each in turn.
+    ///
+    /// The order of `nodes` makes no difference: any order of the same
+    /// nodes, even with others added and removed in between, leaves an
+    /// equal ring that routes every key to the same node.
+    pub fn add_nodes<I: IntoIterator<Item = T>>(&mut self, nodes: I) {
+        for node in nodes {
+            self.add_node(node);
+        }
+    }
+
+    /// Adds `node` to the ring with `weight` times the usual number of
+    /// points, so it owns roughly `weight` times the keyspace of a node
+    /// added with [`add_node`](Self::add_node).
+    ///
+    /// Adding a node that is already on the ring replaces its weight.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `weight` is zero, or if `weight` times the replica count
+    /// overflows `usize`.
+    pub fn add_weighted_node(&mut self, node: T, weight: u32) {
+        self.insert_weighted_node(node, weight);
+    }
+
+    /// Changes the weight of `node`, which is already on the ring, adding
+    /// or removing only as many points as the difference calls for.
+    ///
+    /// A weight gives a node the first `weight` times the replica count of
+    /// its numbered replicas, so raising it keeps every point the node has
+    /// and adds the replicas that come next, and lowering it removes the
+    /// points the node got most recently. Only keys on the points added or
+    /// removed change hands. Unlike
+    /// [`add_weighted_node`](Self::add_weighted_node), this keeps positions
+    /// inherited through [`replace_node`](Self::replace_node) or placed with
+    /// [`add_node_at`](Self::add_node_at), other than any it removes. Does
+    /// nothing if `node` is not on the ring.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `weight` is zero.
+    pub fn set_weight(&mut self, node: &T, weight: u32) {
+        assert!(weight > 0, "a node needs a weight of at least one");
+        let Some(entry) = self.nodes.get(node) else {
+            return;
+        };
+        let old_weight = entry.weight;
+        let held = entry.positions.len();
+        let (before, after) = (
+            self.replicas * old_weight as usize,
+            self.replicas * weight as usize,
+        );
+
+        if after > before {
+            let added: Vec<S::Position> = self.point_positions(node, weight).skip(before).collect();
+            for &position in &added {
+                self.insert_point(position, node.clone());
+            }
+            self.nodes.get_mut(node).unwrap().positions.extend(added);
+        } else {
+            // A node placed with `add_node_at` can hold fewer points than
+            // its weight gives, but it never loses its last one.
+            let keep = held - (before - after).min(held - 1);
+            let entry = self.nodes.get_mut(node).unwrap();
+            let removed = entry.positions.split_off(keep);
+            self.remove_points(node, &removed);
+        }
+        self.nodes.get_mut(node).unwrap().weight = weight;
+    }
+
+    fn insert_weighted_node(&mut self, node: T, weight: u32) -> bool {
+        assert!(weight > 0, "a node needs a weight of at least one");
+        let mut clean = true;
+        if let Some(previous) = self.nodes.remove(&node) {
+            self.remove_points(&node, &previous.positions);
+            clean = false;
+        }
+
+        // The points are hashed one by one, so reserve for all of them
+        // rather than growing the list as they come.
+        let points = self.point_positions(&node, weight);
+        let mut positions = Vec::with_capacity(self.replicas * weight as usize);
+        positions.extend(points);
+        clean & self.place_node(node, weight, positions)
+    }
+
+    /// Places a point for `node` at exactly `position`, without hashing.
+    ///
+    /// This is an escape hatch for matching a layout decided elsewhere,
+    /// point by point, and for tests that need a key to land on a known
+    /// node. A node that is not on the ring yet joins with a weight of one
+    /// and this as its only point; for one that is, the point is added to
+    /// those it has. Such points stay until the node is removed, and are
+    /// kept by [`to_bytes`](Self::to_bytes), but
+    /// [`topology`](Self::topology) cannot describe them. A position another
+    /// node already has a point at is shared as for any collision.
+    pub fn add_node_at(&mut self, node: T, position: S::Position) {
+        match self.nodes.get_mut(&node) {
+            Some(entry) => {
+                entry.positions.push(position);
+                self.insert_point(position, node);
+            }
+            None => {
+                self.place_node(node, 1, vec![position]);
+            }
+        }
+    }
+
+    /// Puts a node that is not yet on the ring at `positions`, returning
+    /// `false` if any of them collided with another node's point.
+    fn place_node(&mut self, node: T, weight: u32, positions: Vec<S::Position>) -> bool {
+        let mut clean = true;
+        for &hash_key in &positions {
+            clean &= self.insert_point(hash_key, node.clone());
+        }
+        let entry = NodeEntry {
+            weight,
+            positions,
+            #[cfg(feature = "metrics")]
+            hits: Default::default(),
+        };
+        self.nodes.insert(node, entry);
+        clean
+    }
+
+    /// Removes every point `node` owns from the ring, and returns the
+    /// ring's own copy of the node, say to close a connection it holds.
+    ///
+    /// Every other node's points stay where they were, so the only keys
+    /// that move are the ones `node` owned. Adding `node` back puts its
+    /// points where they were before, unless it had taken them over with
+    /// [`replace_node`](Self::replace_node).
+    ///
+    /// Returns `None`, leaving the ring unchanged, if `node` is not on it.
+    pub fn remove_node(&mut self, node: &T) -> Option<T> {
+        let (removed, entry) = self.nodes.remove_entry(node)?;
+
+        self.remove_points(node, &entry.positions);
+        self.loads.remove(node);
+        self.draining.remove(node);
+        Some(removed)
+    }
+
+    /// Removes `node` like [`remove_node`](Self::remove_node), and returns
+    /// the positions of the points it had, sorted and each once.
+    ///
+    /// These are the arcs whose keys have to move, for planning the data
+    /// movement a removal causes. A position a colliding node took over is
+    /// included too, since its keys move to that node. Returns an empty
+    /// `Vec` if `node` is not on the ring.
+    pub fn remove_node_reporting(&mut self, node: &T) -> Vec<S::Position> {
+        let Some(entry) = self.nodes.get(node) else {
+            return Vec::new();
+        };
+        let mut positions = entry.positions.clone();
+        positions.sort_unstable();
+        positions.dedup();
+        self.remove_node(node);
+        positions
+    }
+
+    /// Puts `new` on the ring in place of `old`, at exactly the positions
+    /// `old`'s points were at.
+    ///
+    /// Every key `old` owned moves to `new` and no other key moves, which is
+    /// what a rolling replacement of one machine by another wants. `new`
+    /// takes over `old`'s weight and outstanding
+    /// [`load`](Self::load) too. If `new` was already on the ring its own
+    /// points are removed first. The inherited positions stay with `new`
+    /// until it is removed or placed anew with
+    /// [`add_weighted_node`](Self::add_weighted_node).
+    ///
+    /// Does nothing if `old` is not on the ring.
+    pub fn replace_node(&mut self, old: &T, new: T) {
+        if *old == new || !self.contains_node(old) {
+            return;
+        }
+        self.remove_node(&new);
+
+        let Some(entry) = self.nodes.remove(old) else {
+            return;
+        };
+        for &position in &entry.positions {
+            self.remove_point(position, old);
+            self.insert_point(position, new.clone());
+        }
+        if let Some(load) = self.loads.remove(old) {
+            self.loads.insert(new.clone(), load);
+        }
+        self.draining.remove(old);
+        self.nodes.insert(new, entry);
+    }
+
+    /// Moves the ring to `hasher`, which need not be of the same type,
+    /// placing every node again at the positions it hashes to with it and
+    /// keeping each node's weight.
+    ///
+    /// This is for migrating a ring, say from the standard library's hasher
+    /// to a [`FixedHasher`], without rebuilding its membership by hand. Most
+    /// keys move, as they would on any ring built from scratch with the new
+    /// hasher. Positions inherited through
+    /// [`replace_node`](Self::replace_node) are given up for the node's own.
+    /// The salt, replica key, health check, draining nodes and outstanding
+    /// [`load`](Self::load) carry over.
+    pub fn rehash_with<S2: RingHasher>(self, hasher: S2) -> HashRing<T, S2> {
+        let mut ring = HashRing::with_replicas_and_hasher(self.replicas, hasher);
+        ring.salt = self.salt;
+        ring.double_hashing = self.double_hashing;
+        ring.replica_key = self.replica_key;
+        ring.health = self.health;
+        ring.draining = self.draining;
+        ring.loads = self.loads;
+        #[cfg(feature = "metrics")]
+        {
+            ring.lookups = self.lookups;
+        }
+        for (node, entry) in self.nodes {
+            let positions = ring.point_positions(&node, entry.weight).collect();
+            ring.place_node(node, entry.weight, positions);
+        }
+        ring
+    }
+
+    /// Removes every node in `nodes` from the ring, as if by calling
+    /// [`remove_node`](Self::remove_node) on each in turn.
+    pub fn remove_nodes<'a, I: IntoIterator<Item = &'a T>>(&mut self, nodes: I)
+    where
+        T: 'a,
+    {
+        for node in nodes {
+            self.remove_node(node);
+        }
+    }
+
+    /// Adds every node in `additions` and then removes every node in
+    /// `removals`, as one change.
+    ///
+    /// The new layout is built on a copy of the ring and swapped in whole
+    /// once it is complete, so if hashing a node panics partway through the
+    /// ring is left as it was rather than half changed. Through
+    /// `ConcurrentHashRing::apply_changes`, with the `std` feature, readers
+    /// see the ring either before or after the change. A node in both lists
+    /// ends up removed.
+    pub fn apply_changes(&mut self, additions: Vec<T>, removals: Vec<&T>)
+    where
+        S: Clone,
+    {
+        let mut next = self.clone();
+        next.add_nodes(additions);
+        next.remove_nodes(removals);
+        *self = next;
+    }
+
+    /// Removes the node that has a point at exactly `hash` and returns it,
+    /// for when a position is known (say from [`iter`](Self::iter)) but the
+    /// node itself is not.
+    ///
+    /// All of the node's points are removed, not just the one at `hash`.
+    /// Returns `None`, leaving the ring unchanged, if no point sits at
+    /// `hash`.
+    pub fn remove_by_hash(&mut self, hash: S::Position) -> Option<T> {
+        let node = self.keys.get(&hash)?.clone();
+        self.remove_node(&node)
+    }
+
+    /// Returns the positions of the points a node with `weight` is placed
+    /// at: one for each `(node, replica)` pair, with `weight` times the
+    /// ring's replica count of replicas.
+    ///
+    /// The pair itself is hashed unless the ring was built with a
+    /// [`replica_key`](HashRingBuilder::replica_key), in which case the bytes
+    /// it returns for the pair are. Failing that, with
+    /// [double hashing](HashRingBuilder::double_hashing) replica `i` is at
+    /// `h1 + i * step`, where `h1` is where replica 0 would otherwise go and
+    /// `step` is odd, so no two of a node's points ever coincide.
+    fn point_positions<'a>(
+        &'a self,
+        node: &'a T,
+        weight: u32,
+    ) -> impl Iterator<Item = S::Position> + 'a {
+        let double_hashing = self.double_hashing && self.replica_key.is_none();
+        let (first, step) = if double_hashing {
+            let half = self.hash_one(&(node, 1usize));
+            let step = half.wrapping_add(half).wrapping_add(S::Position::ONE);
+            (self.hash_one(&(node, 0usize)), step)
+        } else {
+            (S::Position::ONE, S::Position::ONE)
+        };
+
+        let count = self
+            .replicas
+            .checked_mul(weight as usize)
+            .expect("a node's point count overflows usize");
+        (0..count).scan(first, move |next, replica| {
+            Some(match &self.replica_key {
+                Some(replica_key) => self.hash_one(&RawBytes(&(replica_key.0)(node, replica))),
+                None if double_hashing => {
+                    let position = *next;
+                    *next = next.wrapping_add(step);
+                    position
+                }
+                None => self.hash_one(&(node, replica)),
+            })
+        })
+    }
+
+    /// Places a point for `node` at `position`, returning `false` if the
+    /// position was already held by a different node.
+    fn insert_point(&mut self, position: S::Position, node: T) -> bool {
+        self.routing_changed();
+        let Some(owner) = self.keys.get_mut(&position) else {
+            self.keys.insert(position, node);
+            return true;
+        };
+        if *owner == node {
+            return true;
+        }
+
+        let displaced = if node < *owner {
+            core::mem::replace(owner, node)
+        } else {
+            node
+        };
+        let waiting = self.collisions.entry(position).or_default();
+        if let Err(index) = waiting.binary_search(&displaced) {
+            waiting.insert(index, displaced);
+        }
+        false
+    }
+
+    /// Removes `node`'s point at `position`, handing the position to the
+    /// next colliding node if there is one.
+    fn remove_point(&mut self, position: S::Position, node: &T) {
+        self.routing_changed();
+        let Some(owner) = self.keys.get(&position) else {
+            return;
+        };
+
+        if let btree_map::Entry::Occupied(mut waiting) = self.collisions.entry(position) {
+            if owner == node {
+                let next = waiting.get_mut().remove(0);
+                self.keys.insert(position, next);
+            } else if let Ok(index) = waiting.get().binary_search(node) {
+                waiting.get_mut().remove(index);
+            }
+            if waiting.get().is_empty() {
+                waiting.remove();
+            }
+        } else if owner == node {
+            self.keys.remove(&position);
+        }
+    }
+
+    /// Returns the node that would own `position` once `node` is removed.
+    fn owner_without(&self, position: S::Position, node: &T) -> Option<&T> {
+        match self.keys.get(&position) {
+            Some(owner) if owner == node => self.collisions.get(&position)?.first(),
+            owner => owner,
+        }
+    }
+
+    fn remove_points(&mut self, node: &T, positions: &[S::Position]) {
+        for &hash_key in positions {
+            self.remove_point(hash_key, node);
+        }
+    }
+
+    /// Returns `true` if `node` has been added to the ring.
+    pub fn contains_node(&self, node: &T) -> bool {
+        self.nodes.contains_key(node)
+    }
+
+    /// Returns the node that owns `key`, or `None` if the ring is empty.
+    ///
+    /// With a health check set by [`set_health`](Self::set_health), a key
+    /// whose owner is unhealthy goes to the first healthy node clockwise
+    /// from it instead, and `None` also means no node is healthy. Nodes
+    /// being [drained](Self::drain_node) are skipped the same way.
+    ///
+    /// Any `Hash` type can be used as a key, so the same ring can route, say,
+    /// both string and integer keys. Unsized keys work too: a `&str` routes
+    /// the same as the `String` holding the same text, with no allocation.
+    pub fn get_node<K: Hash + ?Sized>(&self, key: &K) -> Option<&T> {
+        self.get_node_with_hash(key).map(|(node, _)| node)
+    }
+
+    /// Returns a clone of the node that owns `key`, so the ring is no longer
+    /// borrowed once the lookup returns.
+    pub fn get_node_cloned<K: Hash + ?Sized>(&self, key: &K) -> Option<T> {
+        self.get_node(key).cloned()
+    }
+
+    /// Makes [`get_node`](Self::get_node), and every lookup built on it,
+    /// skip nodes for which `health` returns `false`.
+    ///
+    /// `health` is called on each node a lookup reaches until it finds a
+    /// healthy one, so it should be cheap, such as reading a flag kept up to
+    /// date elsewhere. Skipping a node moves only its keys, each to the node
+    /// that would take it if the unhealthy node were removed. Replaces any
+    /// health check already set.
+    pub fn set_health<F>(&mut self, health: F)
+    where
+        F: Fn(&T) -> bool + Send + Sync + 'static,
+    {
+        self.health = Some(HealthCheck(Arc::new(health)));
+        self.routing_changed();
+    }
+
+    /// Removes the health check set by [`set_health`](Self::set_health), so
+    /// lookups reach every node again.
+    pub fn clear_health(&mut self) {
+        self.health = None;
+        self.routing_changed();
+    }
+
+    /// Stops new lookups from going to `node`, ahead of removing it, while
+    /// leaving it on the ring.
+    ///
+    /// [`get_node`](Self::get_node) and the lookups built on it skip a
+    /// draining node as they would an unhealthy one, so its keys go to the
+    /// nodes that will own them once it is gone, while
+    /// [`get_node_allow_draining`](Self::get_node_allow_draining) still
+    /// returns it for requests that were already under way. So do the walks
+    /// that pick several nodes or pass over busy ones, such as
+    /// [`get_nodes`](Self::get_nodes),
+    /// [`get_node_bounded`](Self::get_node_bounded) and
+    /// [`get_node_excluding`](Self::get_node_excluding), and
+    /// [`failover_chain`](Self::failover_chain) moves it to the end. The
+    /// methods that describe the ring's layout rather than route a key,
+    /// such as [`owner_of_position`](Self::owner_of_position),
+    /// [`get_node_range`](Self::get_node_range) and
+    /// [`arcs`](Self::arcs), still count its points, as does
+    /// [`get_node_stable`](Self::get_node_stable).
+    /// [`contains_node`](Self::contains_node) keeps reporting it until
+    /// [`remove_node`](Self::remove_node) finishes the drain. Does nothing
+    /// if `node` is not on the ring.
+    pub fn drain_node(&mut self, node: &T) {
+        if self.nodes.contains_key(node) {
+            self.draining.insert(node.clone());
+            self.routing_changed();
+        }
+    }
+
+    /// Returns `true` if `node` is being drained by
+    /// [`drain_node`](Self::drain_node).
+    pub fn is_draining(&self, node: &T) -> bool {
+        self.draining.contains(node)
+    }
+
+    /// Returns a mutable reference to the node that owns `key`, the one
+    /// [`get_node`](Self::get_node) gives.
+    ///
+    /// The ring keeps a separate copy of a node for each of its points, and
+    /// this is the copy at the point `key` was routed to: another key will see
+    /// the change only if it lands on the same point, and removing or
+    /// re-adding the node discards it. So this suits state attached to a
+    /// particular point, not the node as a whole. Changing anything the
+    /// node's `Hash`, `Eq` or `Ord` impls look at leaves the ring
+    /// inconsistent.
+    pub fn get_node_mut<K: Hash + ?Sized>(&mut self, key: &K) -> Option<&mut T> {
+        let (position, _) = self.available_successor(self.hash_one(key), false)?;
+        self.keys.get_mut(&position)
+    }
+
+    /// Returns the first node clockwise from `key` that is not in
+    /// `exclude`, for retrying a key elsewhere when its owner is known to be
+    /// down.
+    ///
+    /// With nothing excluded this is [`get_node`](Self::get_node), skipping
+    /// the nodes it skips. Returns `None` if every node on the ring is
+    /// excluded or skipped.
+    pub fn get_node_excluding<K: Hash + ?Sized>(&self, key: &K, exclude: &[T]) -> Option<&T> {
+        let hash_key = self.hash_one(key);
+        self.keys
+            .range(hash_key..)
+            .chain(self.keys.range(..hash_key))
+            .map(|(_, node)| node)
+            .find(|node| !exclude.contains(node) && self.is_available(node))
+    }
+
+    /// Returns the node that owns `key` together with the hash `key` was
+    /// given, i.e. the position on the ring the lookup started from.
+    pub fn get_node_with_hash<K: Hash + ?Sized>(&self, key: &K) -> Option<(&T, S::Position)> {
+        let hash_key = self.hash_one(key);
+        self.get_node_for_hash(hash_key)
+            .map(|node| (node, hash_key))
+    }
+
+    /// Returns the node that owns a key whose hash is already known, as
+    /// [`hash_one`](Self::hash_one) gives it, without hashing again.
+    ///
+    /// This is the lookup [`get_node`](Self::get_node) does once it has
+    /// hashed its key, health check, lookup count and all, for pipelines
+    /// where an earlier stage hashed the key already. Unlike
+    /// [`owner_of_position`](Self::owner_of_position) it counts as a lookup
+    /// and skips unhealthy nodes.
+    pub fn get_node_for_hash(&self, hash: S::Position) -> Option<&T> {
+        self.lookup(hash, false)
+    }
+
+    /// Returns the node that owns `key` like [`get_node`](Self::get_node),
+    /// except that nodes being drained by [`drain_node`](Self::drain_node)
+    /// are not skipped.
+    ///
+    /// This is for requests already under way when the drain began, which
+    /// should still reach the node that had their keys until it is removed.
+    pub fn get_node_allow_draining<K: Hash + ?Sized>(&self, key: &K) -> Option<&T> {
+        self.lookup(self.hash_one(key), true)
+    }
+
+    fn lookup(&self, hash: S::Position, allow_draining: bool) -> Option<&T> {
+        let node = if allow_draining {
+            self.available_successor(hash, true)
+        } else {
+            self.cached_successor(hash)
+        }
+        .map(|(_, node)| node);
+
+        #[cfg(feature = "metrics")]
+        {
+            self.lookups.increment();
+            if let Some(node) = node {
+                self.nodes[node].hits.increment();
+            }
+        }
+        node
+    }
+
+    /// Returns the point [`get_node`](Self::get_node) routes `hash` to, by
+    /// way of the lookup cache when the `cache` feature is on.
+    ///
+    /// A health check's answers can change between calls, so while one is
+    /// set every lookup asks it afresh, and the cache is left alone.
+    #[cfg(feature = "cache")]
+    fn cached_successor(&self, hash: S::Position) -> Option<(S::Position, &T)> {
+        if self.health.is_some() {
+            return self.available_successor(hash, false);
+        }
+        let generation = self.cache.generation();
+        if let Some(position) = self.cache.get(hash, generation) {
+            if let Some((&position, node)) = self.keys.get_key_value(&position) {
+                return Some((position, node));
+            }
+        }
+        let (position, node) = self.available_successor(hash, false)?;
+        self.cache.insert(hash, position, generation);
+        Some((position, node))
+    }
+
+    #[cfg(not(feature = "cache"))]
+    fn cached_successor(&self, hash: S::Position) -> Option<(S::Position, &T)> {
+        self.available_successor(hash, false)
+    }
+
+    /// Returns the first point at or clockwise after `hash` whose node
+    /// passes the health check and, unless `allow_draining`, is not being
+    /// drained.
+    fn available_successor(
+        &self,
+        hash: S::Position,
+        allow_draining: bool,
+    ) -> Option<(S::Position, &T)> {
+        let skip_draining = !allow_draining && !self.draining.is_empty();
+        if self.health.is_none() && !skip_draining {
+            return self.successor(hash);
+        }
+        self.keys
+            .range(hash..)
+            .chain(self.keys.range(..hash))
+            .map(|(&position, node)| (position, node))
+            .find(|(_, node)| {
+                !(skip_draining && self.draining.contains(node))
+                    && self.health.as_ref().is_none_or(|health| (health.0)(node))
+            })
+    }
+
+    /// Returns `true` if `node` passes the health check and is not being
+    /// drained, so [`get_node`](Self::get_node) may send keys to it.
+    fn is_available(&self, node: &T) -> bool {
+        !self.draining.contains(node) && self.health.as_ref().is_none_or(|health| (health.0)(node))
+    }
+
+    /// Returns the node that owns `key` together with how far clockwise of
+    /// the key's hash the owning point is, i.e. how deep into the point's
+    /// arc the key sits.
+    ///
+    /// The distance is the owning point's position less the hash, wrapping
+    /// around the ring, so a key that hashes right onto a point is at zero.
+    /// Nodes that [`get_node`](Self::get_node) skips, for failing the
+    /// [health check](Self::set_health) or being
+    /// [drained](Self::drain_node), are passed over here too, and the
+    /// distance is to the point of the node the key went to.
+    pub fn get_node_with_distance<K: Hash + ?Sized>(&self, key: &K) -> Option<(&T, S::Position)> {
+        let hash_key = self.hash_one(key);
+        let (position, node) = self.available_successor(hash_key, false)?;
+        Some((node, arc_len(hash_key, position)))
+    }
+
+    /// Returns the node that owns `key` together with whether the lookup
+    /// wrapped past the top of the ring to get there.
+    ///
+    /// A key wraps when it hashes above the last point it could go to, and
+    /// lands on the lowest one instead. Keys wrapping far more often than
+    /// the share of the ring above [`max_position`](Self::max_position)
+    /// suggests point to a badly placed top node. Nodes that
+    /// [`get_node`](Self::get_node) skips are passed over here too, so
+    /// skipping past the top counts as well.
+    pub fn get_node_wrapped<K: Hash + ?Sized>(&self, key: &K) -> Option<(&T, bool)> {
+        let hash_key = self.hash_one(key);
+        let (position, node) = self.available_successor(hash_key, false)?;
+        Some((node, position < hash_key))
+    }
+
+    /// Returns the node that owns `key`, except that a key hashing above
+    /// every point goes to the lowest node by `Ord` rather than the node of
+    /// the lowest point.
+    ///
+    /// [`get_node`](Self::get_node) wraps such a key round to the lowest
+    /// point, whose node depends on where the hasher happened to put it.
+    /// This ties the arc above [`max_position`](Self::max_position) to node
+    /// identity instead: it belongs to the node that sorts first, whatever
+    /// the positions, which is easier to reason about and to pin down in a
+    /// test. Every other key goes where `get_node` sends it. The price is
+    /// that adding a node that sorts before all the others moves that whole
+    /// arc to it. No health check or drain is applied, and nothing is
+    /// counted. Returns `None` if the ring is empty.
+    pub fn get_node_stable<K: Hash + ?Sized>(&self, key: &K) -> Option<&T> {
+        let hash_key = self.hash_one(key);
+        match self.keys.range(hash_key..).next() {
+            Some((_, node)) => Some(node),
+            None => self.nodes.keys().next(),
+        }
+    }
+
+    /// Returns the node that owns `key` together with the arc of positions
+    /// owned by the point `key` landed on.
+    ///
+    /// The arc runs from just after the previous point up to and including
+    /// the owning point, so it is inclusive at both ends; a half-open range
+    /// could not describe an arc ending at the top of the ring. The arc owned
+    /// by the lowest point wraps past the top, and is returned with a start
+    /// greater than its end: it covers `start` up to the top followed by
+    /// `0..=end`. A point alone on the ring owns every position, which comes
+    /// back the same way as the wrapping arc that starts just after it.
+    pub fn get_node_range<K: Hash + ?Sized>(
+        &self,
+        key: &K,
+    ) -> Option<(&T, RangeInclusive<S::Position>)> {
+        let (end, node) = self.successor(self.hash_one(key))?;
+        let (&previous, _) = self
+            .keys
+            .range(..end)
+            .next_back()
+            .or_else(|| self.keys.last_key_value())?;
+
+        Some((node, previous.wrapping_add(S::Position::ONE)..=end))
+    }
+
+    /// Returns the arc every point owns as `(node, start, end)`, in
+    /// ascending order of the point, for routing tables and plots.
+    ///
+    /// The convention is that of [`get_node_range`](Self::get_node_range):
+    /// an arc runs from just after the previous point up to and including
+    /// its own, inclusive at both ends, and the lowest point's arc wraps
+    /// past the top, coming first with a `start` greater than its `end`
+    /// unless the highest point is at the very top. Together the arcs cover
+    /// every position exactly once. A point alone on the ring owns it all,
+    /// as the arc that starts just after it.
+    pub fn arcs(&self) -> Vec<(&T, S::Position, S::Position)> {
+        let Some((&last, _)) = self.keys.last_key_value() else {
+            return Vec::new();
+        };
+        let mut previous = last;
+        self.keys
+            .iter()
+            .map(|(&position, node)| {
+                let start = previous.wrapping_add(S::Position::ONE);
+                previous = position;
+                (node, start, position)
+            })
+            .collect()
+    }
+
+    /// Returns the node that owns `position`: the node of the first point at
+    /// or clockwise after it, wrapping past the top of the ring.
+    ///
+    /// This is the walk [`get_node`](Self::get_node) does once it has
+    /// hashed its key, leaving out the health check, as
+    /// [`get_node_for_hash`](Self::get_node_for_hash) does not.
+    pub fn owner_of_position(&self, position: S::Position) -> Option<&T> {
+        self.successor(position).map(|(_, node)| node)
+    }
+
+    /// Returns every node that owns a position between `start` and `end`
+    /// inclusive, each once, in the order they are reached walking clockwise
+    /// from `start`.
+    ///
+    /// When `start` is greater than `end` the range wraps past the top of
+    /// the ring, covering `start` up to the top followed by `0..=end`, the
+    /// same way [`get_node_range`](Self::get_node_range) reports the arc
+    /// that wraps. This is for range scans, which have to ask every node
+    /// returned for its part of the range. Returns an empty `Vec` if the
+    /// ring is empty.
+    pub fn nodes_in_range(&self, start: S::Position, end: S::Position) -> Vec<&T> {
+        let Some((_, last)) = self.successor(end) else {
+            return Vec::new();
+        };
+        // The points inside the range own everything in it up to the last of
+        // them, and the rest up to `end` belongs to the point after it.
+        let inside: Vec<&T> = if start <= end {
+            self.keys.range(start..=end).map(|(_, node)| node).collect()
+        } else {
+            self.keys
+                .range(start..)
+                .chain(self.keys.range(..=end))
+                .map(|(_, node)| node)
+                .collect()
+        };
+
+        let mut seen = BTreeSet::new();
+        inside
+            .into_iter()
+            .chain([last])
+            .filter(|&node| seen.insert(node))
+            .collect()
+    }
+
+    /// Returns the first point at or clockwise after `position`, wrapping
+    /// past the top of the ring to the lowest point, or `None` if the ring
+    /// is empty.
+    pub fn successor(&self, position: S::Position) -> Option<(S::Position, &T)> {
+        self.keys
+            .range(position..)
+            .next()
+            .or_else(|| self.keys.first_key_value())
+            .map(|(&position, node)| (position, node))
+    }
+
+    /// Returns the lowest position a point is at, or `None` if the ring is
+    /// empty.
+    ///
+    /// Keys that hash above [`max_position`](Self::max_position) wrap around
+    /// to the point here.
+    pub fn min_position(&self) -> Option<S::Position> {
+        self.keys.first_key_value().map(|(&position, _)| position)
+    }
+
+    /// Returns the highest position a point is at, or `None` if the ring is
+    /// empty.
+    pub fn max_position(&self) -> Option<S::Position> {
+        self.keys.last_key_value().map(|(&position, _)| position)
+    }
+
+    /// Returns the last point at or counterclockwise before `position`,
+    /// wrapping past the bottom of the ring to the highest point, or `None`
+    /// if the ring is empty.
+    pub fn predecessor(&self, position: S::Position) -> Option<(S::Position, &T)> {
+        self.keys
+            .range(..=position)
+            .next_back()
+            .or_else(|| self.keys.last_key_value())
+            .map(|(&position, node)| (position, node))
+    }
+
+    /// Returns the zero-based index, among all points in ascending order of
+    /// position, of the point that owns `key`.
+    ///
+    /// The index lines up with [`ring_points`](Self::ring_points), so it can
+    /// serve as a compact shard id for indexing an array. It is only stable
+    /// while membership is: adding or removing a point below the owning one
+    /// shifts it. Finding it walks the points below the owner, so this is
+    /// linear in the ring's size rather than logarithmic.
+    pub fn get_shard<K: Hash + ?Sized>(&self, key: &K) -> Option<usize> {
+        let (position, _) = self.successor(self.hash_one(key))?;
+        Some(self.keys.range(..position).count())
+    }
+
+    /// Splits the hash space into `shard_count` equal slots and returns the
+    /// node owning the middle of each, as a table to route keys with by
+    /// indexing instead of searching the ring.
+    ///
+    /// With `shard_count` equal to `2^k` for `k` at least one, a key's slot
+    /// is the top `k` bits of its hash, that is
+    /// `hash_one(key) >> (Position::BITS - k)`; with a single slot it is
+    /// `0`. A key near a slot's edge can land on a different node than
+    /// [`get_node`](Self::get_node) would give it, so the table trades a
+    /// little accuracy and balance for constant-time lookups, and the more
+    /// slots the smaller the difference. A slot whose middle falls to an
+    /// unhealthy or draining node goes to the next available one, as in
+    /// [`get_node`](Self::get_node), though building the table does not
+    /// count as lookups. Returns an empty table for an empty ring.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shard_count` is not a power of two, or is not smaller
+    /// than the number of positions on the ring.
+    pub fn shard_table(&self, shard_count: usize) -> Vec<&T> {
+        assert!(
+            shard_count.is_power_of_two(),
+            "the shard count has to be a power of two"
+        );
+        let bits = shard_count.trailing_zeros();
+        assert!(
+            bits < S::Position::BITS,
+            "the shard count has to be below the number of ring positions"
+        );
+        if self.keys.is_empty() {
+            return Vec::new();
+        }
+
+        let shift = S::Position::BITS - bits - 1;
+        (0..shard_count as u128)
+            .filter_map(|slot| {
+                let middle = S::Position::truncate_from((2 * slot + 1) << shift);
+                self.available_successor(middle, false)
+                    .map(|(_, node)| node)
+            })
+            .collect()
+    }
+
+    /// Returns the node that owns the composite key made of `a` and `b`,
+    /// such as a tenant and a key within it.
+    ///
+    /// The pair is hashed as the tuple `(a, b)`, so this is the same as
+    /// `get_node(&(a, b))` and saves building the tuple or concatenating
+    /// strings. Changing either part can change the node.
+    pub fn get_node_composite<A: Hash, B: Hash>(&self, a: &A, b: &B) -> Option<&T> {
+        self.get_node(&(a, b))
+    }
+
+    /// Returns the node every key in `group` goes to, for keeping related
+    /// keys such as all the rows of one user on the same node.
+    ///
+    /// Only the group identifier is hashed, which makes this the same as
+    /// `get_node(group)`: the keys themselves play no part, and every key
+    /// looked up through the same group goes to the same node. Where keys
+    /// should instead spread out within a group, use
+    /// [`get_node_composite`](Self::get_node_composite).
+    pub fn get_node_in_group<G: Hash + ?Sized>(&self, group: &G) -> Option<&T> {
+        self.get_node(group)
+    }
+
+    /// Returns the node that owns the key made up of exactly `bytes`.
+    ///
+    /// The bytes are fed to the hasher as they are, which suits binary keys
+    /// such as content digests or file chunks. This is not the same hash a
+    /// `Hash` impl produces for the same data: `[u8]` and `str` both mix in
+    /// a length or terminator, so `get_node_by_bytes(b"hello")` and
+    /// `get_node(&"hello")` can pick different nodes. Route a given kind of
+    /// key through one method consistently. Unhealthy and draining nodes
+    /// are skipped, and the lookup is counted, as in `get_node`.
+    pub fn get_node_by_bytes(&self, bytes: &[u8]) -> Option<&T> {
+        self.get_node_for_hash(self.hash_one(&RawBytes(bytes)))
+    }
+
+    /// Returns the node that owns `key`.
+    ///
+    /// This is [`get_node`](Self::get_node) with the empty case spelled out
+    /// as [`HashRingError::EmptyRing`], for callers that want to propagate it
+    /// with `?`.
+    pub fn try_get_node<K: Hash + ?Sized>(&self, key: &K) -> Result<&T, HashRingError> {
+        self.get_node(key).ok_or(HashRingError::EmptyRing)
+    }
+
+    /// Builds a ring with one point per node from `nodes` like its
+    /// `From<Vec<T>>` impl, but refuses input that would not place every
+    /// node cleanly.
+    ///
+    /// This is for node lists from configuration, where a repeated node is
+    /// a mistake and a collision, though the ring would cope with it, is
+    /// worth hearing about. It is not a `TryFrom` impl because the `From`
+    /// impl already provides an infallible one. Returns the first
+    /// [`NodeConflict`] found, in list order.
+    pub fn try_from_nodes(nodes: Vec<T>) -> Result<Self, NodeConflict<T>>
+    where
+        S: Default,
+    {
+        let mut hash_ring = Self::default();
+        for node in nodes {
+            if hash_ring.contains_node(&node) {
+                return Err(NodeConflict::Duplicate(node));
+            }
+            let positions: Vec<S::Position> = hash_ring.point_positions(&node, 1).collect();
+            let taken = positions
+                .iter()
+                .find_map(|position| hash_ring.keys.get(position));
+            if let Some(other) = taken {
+                return Err(NodeConflict::Collision {
+                    other: other.clone(),
+                    node,
+                });
+            }
+            hash_ring.place_node(node, 1, positions);
+        }
+        Ok(hash_ring)
+    }
+
+    /// Returns the node that owns `key`, or `default` if the ring is empty.
+    pub fn get_node_or<'a, K: Hash + ?Sized>(&'a self, key: &K, default: &'a T) -> &'a T {
+        self.get_node(key).unwrap_or(default)
+    }
+
+    /// Returns the node that owns `key`, or the result of calling `default`
+    /// if the ring is empty.
+    pub fn get_node_or_else<'a, K: Hash + ?Sized, F: FnOnce() -> &'a T>(
+        &'a self,
+        key: &K,
+        default: F,
+    ) -> &'a T {
+        self.get_node(key).unwrap_or_else(default)
+    }
+
+    /// Routes every key in `keys`, returning each with the node that owns
+    /// it, in the order given.
+    pub fn get_nodes_for_keys<'a, 'k, K: Hash>(
+        &'a self,
+        keys: &'k [K],
+    ) -> Vec<(&'k K, Option<&'a T>)> {
+        keys.iter().map(|key| (key, self.get_node(key))).collect()
+    }
+
+    /// Returns the fraction of the hash space each node owns.
+    ///
+    /// A point owns the arc running back from its position to the previous
+    /// point on the ring, and a node's share is the sum of the arcs of all of
+    /// its points, as a fraction of all the positions on the ring. The
+    /// shares add up to `1.0`. With a single replica per node they can be
+    /// very uneven; with many replicas they approach `1 / len()` for every
+    /// node.
+    pub fn load_distribution(&self) -> BTreeMap<T, f64> {
+        let mut shares = BTreeMap::new();
+        let Some((&last, _)) = self.keys.last_key_value() else {
+            return shares;
+        };
+
+        let mut prev = last;
+        for (&position, node) in &self.keys {
+            // The only way an arc can be empty is for a point to follow
+            // itself, which means it is alone and owns the whole ring.
+            let arc = if position == prev {
+                S::Position::RING_SIZE
+            } else {
+                arc_len(prev, position).to_f64()
+            };
+            *shares.entry(node.clone()).or_insert(0.0) += arc / S::Position::RING_SIZE;
+            prev = position;
+        }
+        shares
+    }
+
+    /// Counts how many of `keys` each node owns.
+    ///
+    /// Where [`load_distribution`](Self::load_distribution) assumes keys are
+    /// spread evenly over the hash space, feeding this a sample of real keys
+    /// shows how a topology handles the actual traffic, hot keys and all.
+    /// Nodes that own none of the keys are left out.
+    pub fn key_histogram<'k, K: Hash + 'k, I: IntoIterator<Item = &'k K>>(
+        &self,
+        keys: I,
+    ) -> BTreeMap<T, usize> {
+        let mut counts = BTreeMap::new();
+        for key in keys {
+            if let Some(node) = self.get_node(key) {
+                *counts.entry(node.clone()).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+
+    /// Groups `keys` by the node that owns each, keeping their order within
+    /// a group, so each group can be handed to its node's worker.
+    ///
+    /// Nodes that own none of the keys are left out, and so are all the
+    /// keys if the ring is empty.
+    pub fn partition_keys<'k, K: Hash>(&self, keys: &'k [K]) -> BTreeMap<T, Vec<&'k K>> {
+        let mut partitions: BTreeMap<T, Vec<&'k K>> = BTreeMap::new();
+        for key in keys {
+            if let Some(node) = self.get_node(key) {
+                partitions.entry(node.clone()).or_default().push(key);
+            }
+        }
+        partitions
+    }
+
+    /// Returns the keys out of `keys` that `node` owns, in the order given.
+    ///
+    /// This is the follow-up to [`key_histogram`](Self::key_histogram) when
+    /// one node's count stands out: it names the sampled keys behind it.
+    pub fn keys_owned_by<'k, K: Hash>(&self, node: &T, keys: &'k [K]) -> Vec<&'k K> {
+        keys.iter()
+            .filter(|key| self.get_node(key) == Some(node))
+            .collect()
+    }
+
+    /// Returns the share of the hash space held by the most loaded node,
+    /// divided by the mean share, as one number to watch for skew.
+    ///
+    /// A perfectly even ring scores `1.0` and the score only grows from
+    /// there: `2.0` means some node owns twice its fair share. Shares are
+    /// those of [`load_distribution`](Self::load_distribution), and the mean
+    /// ignores weights, so a weighted ring scores above `1.0` by design. An
+    /// empty ring scores `1.0`.
+    pub fn imbalance_factor(&self) -> f64 {
+        let shares = self.load_distribution();
+        let max = shares.values().copied().fold(0.0, f64::max);
+        match shares.len() {
+            0 => 1.0,
+            nodes => max * nodes as f64,
+        }
+    }
+
+    /// Removes every point whose clockwise neighbour belongs to the same
+    /// node, and returns how many it removed.
+    ///
+    /// Such a point only splits its node's arc in two, so dropping it leaves
+    /// every key with the owner it had while making lookups walk fewer
+    /// points. A point other nodes collided with is kept, since they are
+    /// waiting to take it over. Every node keeps at least one point.
+    ///
+    /// This is only safe to do while the ring's membership stays as it is:
+    /// the ring no longer matches one built from scratch, so a node added
+    /// afterwards takes keys a dropped point would have kept from it, and
+    /// removing a node can hand different keys to its neighbours. Placing
+    /// the node anew with [`add_weighted_node`](Self::add_weighted_node) or
+    /// [`rehash_with`](Self::rehash_with) restores all of its points.
+    pub fn coalesce(&mut self) -> usize {
+        let points = self.ring_points();
+        let mut redundant: Vec<bool> = points
+            .windows(2)
+            .map(|pair| pair[0].1 == pair[1].1 && !self.collisions.contains_key(&pair[0].0))
+            .collect();
+        // The highest point's arc runs past the top to the lowest point left.
+        let wraps_onto = redundant.iter().position(|&dropped| !dropped);
+        redundant.push(match (wraps_onto, points.last()) {
+            (Some(index), Some(&(position, node))) => {
+                points[index].1 == node && !self.collisions.contains_key(&position)
+            }
+            _ => false,
+        });
+
+        let dropped: Vec<(S::Position, T)> = points
+            .iter()
+            .zip(&redundant)
+            .filter(|(_, &dropped)| dropped)
+            .map(|(&(position, node), _)| (position, node.clone()))
+            .collect();
+        for (position, node) in &dropped {
+            self.remove_point(*position, node);
+            let entry = self.nodes.get_mut(node).unwrap();
+            entry.positions.retain(|held| held != position);
+        }
+        dropped.len()
+    }
+
+    /// Returns the widest arc between two neighbouring points as the
+    /// positions of the points at either end, or `None` if the ring is
+    /// empty.
+    ///
+    /// Every position after `start` up to and including `end` belongs to the
+    /// point at `end`, so this is the single point that takes the most keys,
+    /// and a node or point added halfway between the two evens the ring out
+    /// the most. The arc that runs from [`max_position`](Self::max_position)
+    /// past the top to [`min_position`](Self::min_position) is considered
+    /// too, and comes back with `start` greater than `end`. A ring with one
+    /// point returns that point as both ends, for the whole ring. Of arcs
+    /// that are equally wide, the one ending lowest is returned.
+    pub fn largest_gap(&self) -> Option<(S::Position, S::Position)> {
+        let (&last, _) = self.keys.last_key_value()?;
+        let mut previous = last;
+        let mut widest: Option<(S::Position, S::Position)> = None;
+        for &position in self.keys.keys() {
+            let wider =
+                widest.is_none_or(|(start, end)| arc_len(previous, position) > arc_len(start, end));
+            if wider {
+                widest = Some((previous, position));
+            }
+            previous = position;
+        }
+        widest
+    }
+
+    /// Returns the keys in `sample_keys` that would be routed to a different
+    /// node if `node` were added to the ring with
+    /// [`add_node`](Self::add_node).
+    ///
+    /// Only keys that `node` would take over are affected, so on a balanced
+    /// ring of `n` nodes roughly `1 / (n + 1)` of the sample is returned.
+    /// Nothing moves if `node` is already on the ring.
+    pub fn keys_affected_by_add<'k, K: Hash>(&self, node: &T, sample_keys: &'k [K]) -> Vec<&'k K> {
+        if self.contains_node(node) {
+            return Vec::new();
+        }
+
+        let candidate: BTreeSet<S::Position> = self.point_positions(node, 1).collect();
+        sample_keys
+            .iter()
+            .filter(|key| {
+                let hash_key = self.hash_one(key);
+                let Some((current, owner)) = self.successor(hash_key) else {
+                    return true;
+                };
+                let Some(&added) = candidate
+                    .range(hash_key..)
+                    .next()
+                    .or_else(|| candidate.first())
+                else {
+                    return false;
+                };
+
+                // The candidate wins the key if one of its points comes
+                // first clockwise, or shares the owner's position and sorts
+                // before it.
+                match arc_len(hash_key, added).cmp(&arc_len(hash_key, current)) {
+                    Ordering::Less => true,
+                    Ordering::Equal => node < owner,
+                    Ordering::Greater => false,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the keys in `sample_keys` that `node` owns, each paired with
+    /// the node it would move to if `node` were removed from the ring.
+    ///
+    /// Keys owned by other nodes never move on a removal, so they are left
+    /// out. If `node` is the only node on the ring its keys have nowhere to
+    /// go and the result is empty.
+    pub fn keys_affected_by_remove<'a, 'k, K: Hash>(
+        &'a self,
+        node: &T,
+        sample_keys: &'k [K],
+    ) -> Vec<(&'k K, &'a T)> {
+        sample_keys
+            .iter()
+            .filter_map(|key| {
+                let hash_key = self.hash_one(key);
+                if self.successor(hash_key)?.1 != node {
+                    return None;
+                }
+
+                self.keys
+                    .range(hash_key..)
+                    .chain(self.keys.range(..hash_key))
+                    .find_map(|(&position, _)| self.owner_without(position, node))
+                    .map(|owner| (key, owner))
+            })
+            .collect()
+    }
+
+    /// Routes each of `keys` on both `old` and `new`, returning every key
+    /// with the node that owns it before and after the change.
+    ///
+    /// The entries whose two owners differ are exactly the keys whose data
+    /// has to move; the rest are listed so the plan accounts for the whole
+    /// sample. An owner is `None` where its ring is empty.
+    pub fn migration_plan<'a, 'k, K: Hash>(
+        old: &'a Self,
+        new: &'a Self,
+        keys: &'k [K],
+    ) -> Vec<(&'k K, Option<&'a T>, Option<&'a T>)> {
+        keys.iter()
+            .map(|key| (key, old.get_node(key), new.get_node(key)))
+            .collect()
+    }
+
+    /// Returns a digest of which node each of `keys` goes to, in order, for
+    /// checking that a change meant to leave every key where it was did.
+    ///
+    /// Two calls with the same keys give the same digest exactly when every
+    /// key goes to the same node, barring a collision of the 64-bit hash,
+    /// so a digest taken before a change can be compared with one taken
+    /// after. The digest is taken with [`FixedHasher`], whatever the ring
+    /// hashes with, so it can be compared across processes as long as the
+    /// keys and nodes hash the same.
+    pub fn assignment_digest<K: Hash>(&self, keys: &[K]) -> u64 {
+        let mut hasher = FixedHasher::new().build_hasher();
+        for key in keys {
+            key.hash(&mut hasher);
+            self.get_node(key).hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    /// Returns the fraction of `samples` keys that `old` and `new` give to
+    /// the same node, as one number for how disruptive a change is.
+    ///
+    /// The keys are the same `u64`s on every call, from [`sample_keys`]
+    /// with a fixed seed, so the figure for a given pair of rings is
+    /// reproducible. Adding one node to `n` evenly weighted ones should give
+    /// about `n / (n + 1)`, and anything well below that means more moved
+    /// than had to. Returns `1.0` for no samples.
+    pub fn placement_stability(old: &Self, new: &Self, samples: usize) -> f64 {
+        if samples == 0 {
+            return 1.0;
+        }
+        let kept = sample_keys(0, samples)
+            .iter()
+            .filter(|key| old.get_node(key) == new.get_node(key))
+            .count();
+        kept as f64 / samples as f64
+    }
+
+    /// Routes `key` with consistent hashing with bounded loads, counting the
+    /// assignment against the node it returns.
+    ///
+    /// Each node may carry at most `load_factor` times its fair share of the
+    /// current load, where a node's fair share is proportional to its
+    /// weight. Starting from the node that owns `key`, the lookup walks
+    /// clockwise and returns the first node still under that cap, so a hot
+    /// node sheds keys to its successors instead of being overwhelmed. Call
+    /// [`release`](Self::release) once the returned node has finished the
+    /// work to give the capacity back.
+    ///
+    /// Nodes being [drained](Self::drain_node) are passed over and left out
+    /// of the fair shares. Returns `None` if the ring is empty or every node
+    /// is draining, or if `load_factor` is below `1.0` and every node is
+    /// already at its cap.
+    pub fn get_node_bounded<K: Hash + ?Sized>(&mut self, key: &K, load_factor: f64) -> Option<&T> {
+        let hash_key = self.hash_one(key);
+        let total_load: u64 = self.loads.values().sum();
+        // Draining nodes take no new keys, so the fair shares are of the
+        // weight of the rest.
+        let total_weight: u64 = self
+            .nodes
+            .iter()
+            .filter(|(node, _)| !self.draining.contains(*node))
+            .map(|(_, entry)| u64::from(entry.weight))
+            .sum();
+
+        let position = self
+            .keys
+            .range(hash_key..)
+            .chain(self.keys.range(..hash_key))
+            .filter(|(_, node)| !self.draining.contains(*node))
+            .find(|(_, node)| {
+                let weight = self.nodes[*node].weight as f64;
+                let cap = load_factor * (total_load + 1) as f64 * weight / total_weight as f64;
+                // Loads are whole, so staying under the fractional cap is the
+                // same as staying under it rounded up.
+                (self.load(node) as f64) < cap
+            })
+            .map(|(&position, _)| position)?;
+
+        let node = &self.keys[&position];
+        *self.loads.entry(node.clone()).or_insert(0) += 1;
+        Some(node)
+    }
+
+    /// Routes `key` to the first node clockwise from it whose load in
+    /// `loads` is below `cap`, without touching the ring.
+    ///
+    /// This is the walk [`get_node_bounded`](Self::get_node_bounded) makes,
+    /// for when the loads are tracked outside the ring, say gathered from
+    /// the nodes themselves, and nothing is counted here. A node missing
+    /// from `loads` has no load, and draining nodes are passed over.
+    /// Returns `None` if the ring is empty or every node is draining, at
+    /// `cap` or over it.
+    pub fn get_node_under_load<K: Hash + ?Sized>(
+        &self,
+        key: &K,
+        loads: &BTreeMap<T, u64>,
+        cap: u64,
+    ) -> Option<&T> {
+        let hash_key = self.hash_one(key);
+        self.keys
+            .range(hash_key..)
+            .chain(self.keys.range(..hash_key))
+            .map(|(_, node)| node)
+            .filter(|node| !self.draining.contains(*node))
+            .find(|node| loads.get(node).copied().unwrap_or(0) < cap)
+    }
+
+    /// Returns how many key lookups the ring has answered, in total and per
+    /// node.
+    ///
+    /// Every lookup made with [`get_node`](Self::get_node), or one of the
+    /// methods built on it such as [`try_get_node`](Self::try_get_node),
+    /// counts. [`clear`](Self::clear) resets the counts.
+    #[cfg(feature = "metrics")]
+    pub fn stats(&self) -> RingStats<T> {
+        RingStats {
+            lookups: self.lookups.get(),
+            hits: self
+                .nodes
+                .iter()
+                .map(|(node, entry)| (node.clone(), entry.hits.get()))
+                .collect(),
+        }
+    }
+
+    /// Gives back one unit of load taken from `node` by
+    /// [`get_node_bounded`](Self::get_node_bounded).
+    pub fn release(&mut self, node: &T) {
+        if let Some(load) = self.loads.get_mut(node) {
+            *load -= 1;
+            if *load == 0 {
+                self.loads.remove(node);
+            }
+        }
+    }
+
+    /// Returns the load [`get_node_bounded`](Self::get_node_bounded) has
+    /// assigned to `node` that has not yet been released.
+    pub fn load(&self, node: &T) -> u64 {
+        self.loads.get(node).copied().unwrap_or(0)
+    }
+
+    /// Returns up to `count` distinct nodes for `key`, starting with the node
+    /// that owns it and continuing clockwise around the ring.
+    ///
+    /// Points belonging to a node that has already been collected are
+    /// skipped, so the result never names the same node twice and holds
+    /// every node on the ring when `count` exceeds [`len`](Self::len).
+    /// Nodes being [drained](Self::drain_node) are skipped too, so they are
+    /// handed no new copies; the health check is not applied.
+    ///
+    /// The order is fixed by the ring alone: the points at or after the
+    /// key's hash in ascending order of position, then, once the walk passes
+    /// the top of the ring, the points from the lowest position up to just
+    /// before the hash. A key that hashes above every point starts straight
+    /// from the lowest one.
+    pub fn get_nodes<K: Hash + ?Sized>(&self, key: &K, count: usize) -> Vec<&T> {
+        self.get_nodes_with_positions(key, count)
+            .into_iter()
+            .map(|(node, _)| node)
+            .collect()
+    }
+
+    /// Returns up to `count` distinct nodes for the routing key `f` derives
+    /// from `key`, as [`get_nodes`](Self::get_nodes) gives them for it.
+    ///
+    /// This is for storage that places a record's replicas by one of its
+    /// fields, such as a partition key, rather than by the whole record:
+    /// records that share the field share their nodes.
+    pub fn get_nodes_by<R, K, F>(&self, key: &R, f: F, count: usize) -> Vec<&T>
+    where
+        R: ?Sized,
+        K: Hash,
+        F: Fn(&R) -> K,
+    {
+        self.get_nodes(&f(key), count)
+    }
+
+    /// Returns every node on the ring that is not being
+    /// [drained](Self::drain_node), in the order a walk clockwise from
+    /// `key` first reaches them; the first is the node that owns `key`.
+    ///
+    /// This is [`get_nodes`](Self::get_nodes) with no limit on the count.
+    pub fn nodes_clockwise_from<K: Hash + ?Sized>(&self, key: &K) -> Vec<&T> {
+        self.get_nodes(key, self.len())
+    }
+
+    /// Returns every node on the ring in the order to try them for `key`,
+    /// starting with the node [`get_node`](Self::get_node) gives, so a
+    /// client can move down the list until one answers.
+    ///
+    /// The nodes come in the order a walk clockwise from `key` first
+    /// reaches them, except that nodes failing the
+    /// [health check](Self::set_health) or being
+    /// [drained](Self::drain_node) are moved to the end, still in clockwise
+    /// order, as the last ones to try.
+    pub fn failover_chain<K: Hash + ?Sized>(&self, key: &K) -> Vec<&T> {
+        let hash_key = self.hash_one(key);
+        let mut seen = BTreeSet::new();
+        let (mut chain, rest): (Vec<&T>, Vec<&T>) = self
+            .keys
+            .range(hash_key..)
+            .chain(self.keys.range(..hash_key))
+            .map(|(_, node)| node)
+            .filter(|node| seen.insert(*node))
+            .take(self.len())
+            .partition(|node| self.is_available(node));
+        chain.extend(rest);
+        chain
+    }
+
+    /// Returns the node that owns `key` as the primary, with up to
+    /// `replica_count` further distinct nodes clockwise from it to hold its
+    /// backups.
+    ///
+    /// These are the nodes of [`get_nodes`](Self::get_nodes) with one more
+    /// asked for, split into the first and the rest. Returns `None` if the
+    /// ring is empty or every node on it is draining.
+    pub fn get_primary_and_replicas<K: Hash + ?Sized>(
+        &self,
+        key: &K,
+        replica_count: usize,
+    ) -> Option<ReplicaSet<&T>> {
+        let mut nodes = self.get_nodes(key, replica_count.saturating_add(1));
+        if nodes.is_empty() {
+            return None;
+        }
+        let primary = nodes.remove(0);
+        Some(ReplicaSet {
+            primary,
+            replicas: nodes,
+        })
+    }
+
+    /// Returns the same nodes as [`get_nodes`](Self::get_nodes), each with
+    /// the position of the point it was first reached at.
+    ///
+    /// The positions show how the walk from `key` went around the ring: they
+    /// ascend from the key's hash and drop back once it wraps past the top.
+    pub fn get_nodes_with_positions<K: Hash + ?Sized>(
+        &self,
+        key: &K,
+        count: usize,
+    ) -> Vec<(&T, S::Position)> {
+        let hash_key = self.hash_one(key);
+
+        let mut seen = BTreeSet::new();
+        self.keys
+            .range(hash_key..)
+            .chain(self.keys.range(..hash_key))
+            .filter(|(_, node)| !self.draining.contains(*node) && seen.insert(*node))
+            .take(count.min(self.len() - self.draining.len()))
+            .map(|(&position, node)| (node, position))
+            .collect()
+    }
+
+    /// Returns the node for `key`, out of the first three distinct nodes
+    /// clockwise from it, that scores highest on `bias(node)` less its
+    /// distance from the key's hash.
+    ///
+    /// The distance is counted in average gaps between points, the ring's
+    /// size over its number of points, so a bias of `1.0` makes a node worth
+    /// choosing over the owner when it is about one gap further along. A
+    /// key well inside its owner's arc stays put unless the bias is large;
+    /// one that lands just short of a favoured node's point moves to it.
+    /// Equal scores go to the nearer node, so a bias that is the same for
+    /// every node gives [`get_node`](Self::get_node)'s answer.
+    ///
+    /// This deliberately gives up consistency for locality, such as
+    /// preferring nodes in the caller's zone: callers with different biases
+    /// route the same key to different nodes, and changing a bias moves
+    /// keys with no change in membership. Draining nodes are skipped, but
+    /// health checks are not applied. Returns `None` if the ring is empty.
+    pub fn get_node_biased<K, F>(&self, key: &K, bias: F) -> Option<&T>
+    where
+        K: Hash + ?Sized,
+        F: Fn(&T) -> f64,
+    {
+        let hash_key = self.hash_one(key);
+        let gap = S::Position::RING_SIZE / self.keys.len() as f64;
+
+        let mut best: Option<(&T, f64)> = None;
+        for (node, position) in self.get_nodes_with_positions(key, CANDIDATE_NODES) {
+            let distance = arc_len(hash_key, position).to_f64() / gap;
+            let score = bias(node) - distance;
+            if best.is_none_or(|(_, best_score)| score > best_score) {
+                best = Some((node, score));
+            }
+        }
+        best.map(|(node, _)| node)
+    }
+
+    /// Returns the node for `key`, out of the first three distinct nodes
+    /// clockwise from it, that is nearest once each node's distance from
+    /// the key's hash is divided by the live capacity `capacity` reports
+    /// for it.
+    ///
+    /// A node with twice the capacity of its neighbours reaches twice as far
+    /// back for keys, taking some of those its predecessors would get, and a
+    /// node reporting zero gets nothing, its keys going to the next node
+    /// along. This approximates weighting nodes by capacity that changes
+    /// too often to rebuild the ring for, such as under autoscaling, without
+    /// touching the ring: equal capacities give
+    /// [`get_node`](Self::get_node)'s answer, but keys only ever move
+    /// between nearby nodes, so it cannot make up for large differences.
+    /// Draining nodes are skipped, but health checks are not applied.
+    /// Returns `None` if the ring is empty or every one of the nodes
+    /// reports zero.
+    pub fn get_node_dynamic<K, F>(&self, key: &K, capacity: F) -> Option<&T>
+    where
+        K: Hash + ?Sized,
+        F: Fn(&T) -> u32,
+    {
+        let hash_key = self.hash_one(key);
+        let mut best: Option<(&T, f64)> = None;
+        for (node, position) in self.get_nodes_with_positions(key, CANDIDATE_NODES) {
+            let capacity = capacity(node);
+            if capacity == 0 {
+                continue;
+            }
+            let reach = arc_len(hash_key, position).to_f64() / f64::from(capacity);
+            if best.is_none_or(|(_, best_reach)| reach < best_reach) {
+                best = Some((node, reach));
+            }
+        }
+        best.map(|(node, _)| node)
+    }
+
+    /// Checks that the ring's internal tables agree with each other,
+    /// describing the first disagreement found.
+    ///
+    /// Every public method keeps them in step, so this should never fail;
+    /// it is there for tests and debug assertions around code that changes
+    /// membership. It looks at every point, so it is slow on large rings.
+    pub fn check_invariants(&self) -> Result<(), String>
+    where
+        T: fmt::Debug,
+    {
+        let recorded: BTreeSet<(&T, &S::Position)> = self
+            .nodes
+            .iter()
+            .flat_map(|(node, entry)| entry.positions.iter().map(move |position| (node, position)))
+            .collect();
+
+        for (node, entry) in &self.nodes {
+            // Points placed with `add_node_at` come on top of the hashed
+            // ones, so the count is only known to be nonzero.
+            if entry.positions.is_empty() {
+                return Err(format!("{node:?} has no positions"));
+            }
+            for position in &entry.positions {
+                let placed = match self.keys.get(position) {
+                    Some(owner) if owner == node => true,
+                    Some(_) => self
+                        .collisions
+                        .get(position)
+                        .is_some_and(|waiting| waiting.binary_search(node).is_ok()),
+                    None => false,
+                };
+                if !placed {
+                    return Err(format!("{node:?} has no point at {position:?}"));
+                }
+            }
+        }
+
+        for (position, owner) in &self.keys {
+            if !recorded.contains(&(owner, position)) {
+                return Err(format!(
+                    "the point at {position:?} belongs to {owner:?}, which does not list it"
+                ));
+            }
+        }
+
+        for (position, waiting) in &self.collisions {
+            let Some(owner) = self.keys.get(position) else {
+                return Err(format!("nodes wait for {position:?}, which has no owner"));
+            };
+            if waiting.is_empty() {
+                return Err(format!("the collision list at {position:?} is empty"));
+            }
+            if waiting.windows(2).any(|pair| pair[0] >= pair[1]) {
+                return Err(format!("the collision list at {position:?} is not sorted"));
+            }
+            if waiting[0] <= *owner {
+                return Err(format!(
+                    "{owner:?} owns {position:?} ahead of the smaller {:?}",
+                    waiting[0]
+                ));
+            }
+            for node in waiting {
+                if !recorded.contains(&(node, position)) {
+                    return Err(format!(
+                        "{node:?} waits for {position:?}, which it does not list"
+                    ));
+                }
+            }
+        }
+
+        for (node, load) in &self.loads {
+            if !self.nodes.contains_key(node) {
+                return Err(format!("{node:?} carries load but is not on the ring"));
+            }
+            if *load == 0 {
+                return Err(format!("{node:?} has a zero load recorded"));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: Ord + fmt::Display, S: RingHasher> HashRing<T, S> {
+    /// Draws the ring as text, one row per node, for eyeballing how its
+    /// points are spread.
+    ///
+    /// Each row is `width` columns running from position `0` on the left to
+    /// the top of the ring on the right, with a `|` in every column where
+    /// the node owns a point and a `-` elsewhere, followed by a space and
+    /// the node as displayed. Points close enough together share a column.
+    pub fn ascii_ring(&self, width: usize) -> String {
+        let mut rows: BTreeMap<&T, Vec<char>> = self
+            .nodes
+            .keys()
+            .map(|node| (node, vec!['-'; width]))
+            .collect();
+        if let Some(last_column) = width.checked_sub(1) {
+            for (&position, node) in &self.keys {
+                let column = position.to_f64() / S::Position::RING_SIZE * width as f64;
+                if let Some(row) = rows.get_mut(node) {
+                    row[(column as usize).min(last_column)] = '|';
+                }
+            }
+        }
+
+        let mut drawing = String::new();
+        for (node, row) in rows {
+            drawing.extend(row);
+            let _ = writeln!(drawing, " {node}");
+        }
+        drawing
+    }
+}
+
+impl<T, S: RingHasher> HashRing<T, S> {
+    /// Returns the number of points placed per node, per unit of weight.
+    pub fn replicas(&self) -> usize {
+        self.replicas
+    }
+
+    /// Removes every node from the ring, keeping its hasher and replica
+    /// count so it can be repopulated as if freshly constructed.
+    pub fn clear(&mut self) {
+        self.keys.clear();
+        self.collisions.clear();
+        self.nodes.clear();
+        self.loads.clear();
+        self.draining.clear();
+        self.routing_changed();
+        #[cfg(feature = "metrics")]
+        {
+            self.lookups = Default::default();
+        }
+    }
+
+    /// Notes that keys may have changed owners, so the lookup cache's
+    /// answers can no longer be trusted.
+    fn routing_changed(&self) {
+        #[cfg(feature = "cache")]
+        self.cache.invalidate();
+    }
+
+    /// Releases memory held for points and nodes that have since been
+    /// removed.
+    ///
+    /// The ring's maps are B-trees, which free their nodes as entries are
+    /// removed and have no spare capacity to give back. What can hold on to
+    /// memory are the lists of nodes waiting behind a collision, which
+    /// shrink as nodes leave but keep their allocations, so after a bulk
+    /// removal this trims those and each node's list of positions to size.
+    pub fn shrink_to_fit(&mut self) {
+        for waiting in self.collisions.values_mut() {
+            waiting.shrink_to_fit();
+        }
+        for entry in self.nodes.values_mut() {
+            entry.positions.shrink_to_fit();
+        }
+    }
+
+    /// Returns an iterator over every point on the ring as
+    /// `(position, node)`, in ascending order of position.
+    ///
+    /// A node appears once for each of its replicas; use
+    /// [`nodes`](Self::nodes) to visit each node only once.
+    pub fn iter(&self) -> Iter<'_, T, S::Position> {
+        Iter {
+            inner: self.keys.iter(),
+        }
+    }
+
+    /// Returns every point on the ring as `(position, node)`, sorted by
+    /// ascending position.
+    ///
+    /// This is [`iter`](Self::iter) collected, for plotting and other tools
+    /// that want the whole layout at once.
+    pub fn ring_points(&self) -> Vec<(S::Position, &T)> {
+        self.keys
+            .iter()
+            .map(|(&position, node)| (position, node))
+            .collect()
+    }
+
+    /// Returns the ring as a flat list of `(position, node)` to search
+    /// without the ring, say from the other side of an FFI boundary.
+    ///
+    /// The contents are those of [`ring_points`](Self::ring_points), with a
+    /// guarantee made for lookups: positions are strictly ascending, each
+    /// appears once, and it comes with the node that owns it, so a key goes
+    /// to the node of the first entry at or above its
+    /// [`hash_one`](Self::hash_one), or of the first entry if there is none.
+    /// That is the node [`get_node`](Self::get_node) gives while no
+    /// [health check](Self::set_health) is set and no node is
+    /// [draining](Self::drain_node). Tuples have no fixed memory
+    /// layout, so copy the entries into a `#[repr(C)]` type before handing
+    /// them across.
+    pub fn as_sorted_slice(&self) -> Vec<(S::Position, &T)> {
+        self.ring_points()
+    }
+
+    /// Returns an iterator over the distinct nodes on the ring, in ascending
+    /// order.
+    pub fn nodes(&self) -> impl Iterator<Item = &T> {
+        self.nodes.keys()
+    }
+
+    /// Consumes the ring and returns each node on it once, in ascending
+    /// order, without cloning any of them.
+    pub fn into_nodes(self) -> Vec<T> {
+        self.nodes.into_keys().collect()
+    }
+}
+
+/// The nodes responsible for a key, returned by
+/// [`HashRing::get_primary_and_replicas`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplicaSet<T> {
+    /// The node that owns the key.
+    pub primary: T,
+    /// The nodes that hold backups of the key, in the order they follow
+    /// the primary around the ring. None of them is the primary, and there
+    /// are fewer than asked for if the ring is too small.
+    pub replicas: Vec<T>,
+}
+
+/// An iterator over the points of a [`HashRing`], created by
+/// [`HashRing::iter`].
+#[derive(Debug, Clone)]
+pub struct Iter<'a, T, P = u64> {
+    inner: btree_map::Iter<'a, P, T>,
+}
+
+impl<'a, T, P> Iterator for Iter<'a, T, P> {
+    type Item = (&'a P, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<T, P> DoubleEndedIterator for Iter<'_, T, P> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back()
+    }
+}
+
+impl<T, P> ExactSizeIterator for Iter<'_, T, P> {}
+
+/// The function a ring turns a node and replica number into the bytes of
+/// that replica's point with, set by [`HashRingBuilder::replica_key`].
+pub(crate) struct ReplicaKey<T>(pub(crate) Arc<ReplicaKeyFn<T>>);
+
+type ReplicaKeyFn<T> = dyn Fn(&T, usize) -> Vec<u8> + Send + Sync;
+
+impl<T> Clone for ReplicaKey<T> {
+    fn clone(&self) -> Self {
+        ReplicaKey(Arc::clone(&self.0))
+    }
+}
+
+impl<T> fmt::Debug for ReplicaKey<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ReplicaKey(..)")
+    }
+}
+
+/// The predicate set by [`HashRing::set_health`].
+struct HealthCheck<T>(Arc<HealthFn<T>>);
+
+type HealthFn<T> = dyn Fn(&T) -> bool + Send + Sync;
+
+impl<T> Clone for HealthCheck<T> {
+    fn clone(&self) -> Self {
+        HealthCheck(Arc::clone(&self.0))
+    }
+}
+
+impl<T> fmt::Debug for HealthCheck<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("HealthCheck(..)")
+    }
+}
+
+/// Hashes as exactly the bytes it wraps, with no length prefix.
+struct RawBytes<'a>(&'a [u8]);
+
+impl Hash for RawBytes<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write(self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Hashes to positions that can be worked out by hand: a `u64` or
+    /// `usize` adds its value, so a `u64` key hashes to itself and replica
+    /// `r` of a `u64` node `n` goes to `n + r`.
+    #[derive(Default)]
+    pub(crate) struct IdentityHasher(u64);
+
+    impl Hasher for IdentityHasher {
+        fn finish(&self) -> u64 {
+            self.0
+        }
+
+        fn write(&mut self, bytes: &[u8]) {
+            for &byte in bytes {
+                self.0 = self.0.rotate_left(8) ^ u64::from(byte);
+            }
+        }
+
+        fn write_u64(&mut self, i: u64) {
+            self.0 = self.0.wrapping_add(i);
+        }
+
+        fn write_usize(&mut self, i: usize) {
+            self.0 = self.0.wrapping_add(i as u64);
+        }
+    }
+
+    pub(crate) type Identity = core::hash::BuildHasherDefault<IdentityHasher>;
+
+    /// Hashes everything to the same position, to force collisions.
+    #[derive(Default)]
+    pub(crate) struct ConstantHasher;
+
+    impl Hasher for ConstantHasher {
+        fn finish(&self) -> u64 {
+            1 << 63
+        }
+
+        fn write(&mut self, _: &[u8]) {}
+    }
+
+    pub(crate) type Constant = core::hash::BuildHasherDefault<ConstantHasher>;
+
+    #[test]
+    fn remove_node_removes_the_node_given() {
+        let mut ring = HashRing::new();
+        ring.add_nodes(["a", "b", "c"]);
+        let before = ring.clone();
+        let points: Vec<(u64, &str)> = ring.iter().map(|(&at, &node)| (at, node)).collect();
+        let (first, middle, last) = (points[0], points[1].1, points[2]);
+
+        assert_eq!(ring.remove_node(&middle), Some(middle));
+        assert!(!ring.contains_node(&middle));
+        assert!(ring.contains_node(&first.1) && ring.contains_node(&last.1));
+        assert!(ring.iter().map(|(&at, &node)| (at, node)).eq([first, last]));
+
+        ring.add_node(middle);
+        assert_eq!(ring, before);
+    }
+
+    #[test]
+    fn get_node_matches_a_linear_scan_of_the_points() {
+        let mut ring = HashRing::with_replicas(16);
+        ring.add_nodes(0..50u32);
+        for key in sample_keys(7, 10_000) {
+            let hash = ring.hash_one(&key);
+            let scanned = ring
+                .iter()
+                .find(|(&position, _)| position >= hash)
+                .or_else(|| ring.iter().next())
+                .map(|(_, node)| node);
+            assert_eq!(ring.get_node(&key), scanned);
+        }
+    }
+
+    #[test]
+    fn replicas_spread_keys_evenly() {
+        let mut ring = HashRing::with_replicas(256);
+        ring.add_nodes(0..10u32);
+        let keys = sample_keys(3, 100_000);
+        let counts = ring.key_histogram(&keys);
+        assert_eq!(counts.len(), 10);
+        for (node, count) in counts {
+            let share = count as f64 / keys.len() as f64;
+            assert!((0.08..0.12).contains(&share), "node {node} got {share}");
+        }
+    }
+
+    #[test]
+    fn len_and_is_empty_track_membership() {
+        let mut ring = HashRing::<u32>::default();
+        assert!(ring.is_empty());
+        assert_eq!(ring.len(), 0);
+
+        ring.add_node(1);
+        assert!(!ring.is_empty());
+        ring.add_nodes([2, 3]);
+        assert_eq!(ring.len(), 3);
+
+        ring.remove_node(&2);
+        assert_eq!(ring.len(), 2);
+        ring.remove_nodes(&[1, 3]);
+        assert!(ring.is_empty());
+    }
+
+    #[test]
+    fn contains_node_follows_add_and_remove() {
+        let mut ring = HashRing::with_replicas(4);
+        assert!(!ring.contains_node(&"a"));
+        ring.add_node("a");
+        assert!(ring.contains_node(&"a"));
+        assert!(!ring.contains_node(&"b"));
+        ring.remove_node(&"a");
+        assert!(!ring.contains_node(&"a"));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn lookups_run_from_many_threads_at_once() {
+        let mut ring = HashRing::with_replicas(32);
+        ring.add_nodes(0..8u32);
+        let keys = sample_keys(4, 1_000);
+        let expected: Vec<Option<&u32>> = keys.iter().map(|key| ring.get_node(key)).collect();
+
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for (key, owner) in keys.iter().zip(&expected) {
+                        assert_eq!(ring.get_node(key), *owner);
+                    }
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn get_nodes_returns_distinct_nodes_up_to_the_count() {
+        let mut ring = HashRing::with_replicas(8);
+        ring.add_nodes(0..5u32);
+
+        let three = ring.get_nodes(&"key", 3);
+        assert_eq!(three.len(), 3);
+        assert_eq!(three[0], ring.get_node(&"key").unwrap());
+
+        let mut all = ring.get_nodes(&"key", 10);
+        assert_eq!(&all[..3], &three[..]);
+        all.sort();
+        assert_eq!(all, [&0, &1, &2, &3, &4]);
+
+        assert!(ring.get_nodes(&"key", 0).is_empty());
+        assert!(HashRing::<u32>::new().get_nodes(&"key", 3).is_empty());
+    }
+
+    #[test]
+    fn rings_with_the_same_seeded_hasher_route_alike() {
+        let build = |seed| {
+            let mut ring = HashRing::with_replicas_and_hasher(16, FixedHasher::with_seed(seed));
+            ring.add_nodes(["a", "b", "c", "d"]);
+            ring
+        };
+        let (first, second, other) = (build(42), build(42), build(43));
+
+        let keys = sample_keys(9, 1_000);
+        assert!(keys
+            .iter()
+            .all(|key| first.get_node(key) == second.get_node(key)));
+        assert!(keys
+            .iter()
+            .any(|key| first.get_node(key) != other.get_node(key)));
+    }
+
+    #[test]
+    fn weights_scale_a_nodes_share_of_keys() {
+        let mut ring = HashRing::with_replicas(200);
+        ring.add_weighted_node("light", 1);
+        ring.add_weighted_node("heavy", 3);
+        let keys = sample_keys(10, 100_000);
+        let counts = ring.key_histogram(&keys);
+
+        let light = counts[&"light"] as f64 / keys.len() as f64;
+        let heavy = counts[&"heavy"] as f64 / keys.len() as f64;
+        assert!((0.21..0.29).contains(&light), "light node got {light}");
+        assert!((0.71..0.79).contains(&heavy), "heavy node got {heavy}");
+    }
+
+    #[test]
+    fn try_get_node_reports_an_empty_ring() {
+        let mut ring = HashRing::new();
+        assert_eq!(ring.try_get_node(&"key"), Err(HashRingError::EmptyRing));
+        ring.add_node("a");
+        assert_eq!(ring.try_get_node(&"key"), Ok(&"a"));
+        ring.remove_node(&"a");
+        assert_eq!(ring.try_get_node(&"key"), Err(HashRingError::EmptyRing));
+    }
+
+    #[test]
+    fn iter_is_sorted_and_nodes_are_listed_once() {
+        let mut ring = HashRing::with_replicas(10);
+        ring.add_nodes(["b", "a", "c"]);
+
+        let positions: Vec<u64> = ring.iter().map(|(&position, _)| position).collect();
+        assert_eq!(positions.len(), 30);
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!((&ring).into_iter().eq(ring.iter()));
+
+        assert!(ring.nodes().eq(&["a", "b", "c"]));
+    }
+
+    #[test]
+    fn clear_empties_the_ring_but_keeps_its_settings() {
+        let mut ring = HashRing::builder().replicas(8).salt(5).build();
+        ring.add_nodes(["a", "b"]);
+        ring.clear();
+        assert!(ring.is_empty());
+        assert_eq!(ring.get_node(&"key"), None);
+        assert_eq!(ring.iter().len(), 0);
+
+        ring.add_node("c");
+        let fresh = HashRing::builder().replicas(8).salt(5).node("c").build();
+        assert_eq!(ring, fresh);
+        assert_eq!(ring.get_node(&"key"), Some(&"c"));
+    }
+
+    #[test]
+    fn add_nodes_matches_adding_one_at_a_time() {
+        let mut batched = HashRing::with_replicas(4);
+        batched.add_node(0u32);
+        let mut looped = batched.clone();
+
+        batched.add_nodes(1..6);
+        for node in 1..6 {
+            looped.add_node(node);
+        }
+        assert_eq!(batched, looped);
+        assert!(batched.nodes().eq(&[0, 1, 2, 3, 4, 5]));
+    }
+
+    #[test]
+    fn collecting_places_nodes_as_from_vec_does() {
+        let nodes = vec!["a", "b", "c", "d"];
+        let collected: HashRing<&str> = nodes.iter().copied().collect();
+        assert_eq!(collected, HashRing::from(nodes));
+    }
+
+    #[test]
+    fn get_node_with_hash_reports_the_hash_looked_up() {
+        let mut ring = HashRing::with_replicas(8);
+        ring.add_nodes(["a", "b", "c"]);
+        for key in sample_keys(17, 100) {
+            let (node, hash) = ring.get_node_with_hash(&key).unwrap();
+            assert_eq!(hash, ring.hash_one(&key));
+            assert_eq!(Some(node), ring.get_node(&key));
+            assert_eq!(Some(node), ring.owner_of_position(hash));
+        }
+    }
+
+    #[test]
+    fn load_distribution_sums_the_arcs_behind_each_point() {
+        let mut ring = HashRing::new();
+        ring.add_node_at("a", 1 << 62);
+        ring.add_node_at("b", 1 << 63);
+        ring.add_node_at("c", 3 << 62);
+        ring.add_node_at("a", (3 << 62) + (1 << 61));
+
+        // `a` owns the arc up to its first point, wrapping past the top, and
+        // the eighth of the ring before its second.
+        let shares = ring.load_distribution();
+        assert_eq!(shares[&"a"], 0.375 + 0.125);
+        assert_eq!(shares[&"b"], 0.25);
+        assert_eq!(shares[&"c"], 0.25);
+        assert_eq!(shares.values().sum::<f64>(), 1.0);
+    }
+
+    #[test]
+    fn one_ring_routes_keys_of_different_types() {
+        let mut ring = HashRing::with_replicas(8);
+        ring.add_nodes(["a", "b", "c"]);
+
+        let by_str = ring.get_node("key").unwrap();
+        let by_int = ring.get_node(&42u64).unwrap();
+        assert_eq!(ring.get_node("key"), Some(by_str));
+        assert_eq!(ring.get_node(&42u64), Some(by_int));
+        assert_eq!(ring.get_node(&(7u8, "key")), ring.get_node(&(7u8, "key")));
+    }
+
+    #[test]
+    fn keys_affected_by_add_are_the_ones_the_new_node_takes() {
+        let mut ring = HashRing::with_replicas(100);
+        ring.add_nodes(0..9u32);
+        let keys = sample_keys(20, 20_000);
+
+        let affected = ring.keys_affected_by_add(&9, &keys);
+        let share = affected.len() as f64 / keys.len() as f64;
+        assert!((0.08..0.12).contains(&share), "{share} of keys moved");
+
+        let mut grown = ring.clone();
+        grown.add_node(9);
+        let moved: Vec<&u64> = keys
+            .iter()
+            .filter(|key| ring.get_node(key) != grown.get_node(key))
+            .collect();
+        assert_eq!(affected, moved);
+        assert!(moved.iter().all(|key| grown.get_node(key) == Some(&9)));
+        assert!(ring.keys_affected_by_add(&3, &keys).is_empty());
+    }
+
+    #[test]
+    fn bounded_loads_cap_every_node_under_a_hot_key() {
+        let mut ring = HashRing::with_replicas(16);
+        ring.add_nodes(0..8u32);
+
+        // Every request is for the same key, which plain consistent hashing
+        // would send to one node.
+        for assigned in 1..=4_000u64 {
+            assert!(ring.get_node_bounded(&"hot", 1.25).is_some());
+            let mean = assigned as f64 / 8.0;
+            let max = (0..8).map(|node| ring.load(&node)).max().unwrap();
+            assert!(
+                max as f64 <= (1.25 * mean).ceil(),
+                "{max} over a mean of {mean}"
+            );
+        }
+        assert!((0..8).filter(|node| ring.load(node) > 0).count() > 1);
+
+        let owner = *ring.get_node(&"hot").unwrap();
+        let before = ring.load(&owner);
+        ring.release(&owner);
+        assert_eq!(ring.load(&owner), before - 1);
+        assert_eq!(ring.get_node_bounded(&"hot", 1.25), Some(&owner));
+    }
+
+    #[test]
+    fn remove_by_hash_removes_only_the_node_at_that_position() {
+        let mut ring = HashRing::with_replicas(4);
+        ring.add_nodes(["a", "b"]);
+        let before = ring.clone();
+
+        let (&position, _) = ring.iter().find(|(_, &node)| node == "a").unwrap();
+        let absent = (0..)
+            .find(|hash| ring.iter().all(|(&at, _)| at != *hash))
+            .unwrap();
+        assert_eq!(ring.remove_by_hash(absent), None);
+        assert_eq!(ring, before);
+
+        assert_eq!(ring.remove_by_hash(position), Some("a"));
+        assert!(ring.nodes().eq(&["b"]));
+        assert_eq!(ring.iter().len(), 4);
+    }
+
+    #[test]
+    fn a_clone_changes_independently_of_its_source() {
+        let mut ring = HashRing::with_replicas(8);
+        ring.add_nodes(["a", "b", "c"]);
+        let keys = sample_keys(23, 1_000);
+        let owners: Vec<Option<&str>> =
+            keys.iter().map(|key| ring.get_node(key).copied()).collect();
+
+        let mut clone = ring.clone();
+        clone.add_node("d");
+        assert!(keys.iter().any(|key| clone.get_node(key) == Some(&"d")));
+        assert!(keys
+            .iter()
+            .zip(&owners)
+            .all(|(key, owner)| ring.get_node(key).copied() == *owner));
+    }
+
+    #[test]
+    fn get_node_cloned_matches_get_node() {
+        let mut ring = HashRing::with_replicas(8);
+        ring.add_nodes([String::from("a"), String::from("b")]);
+        for key in sample_keys(24, 100) {
+            assert_eq!(ring.get_node_cloned(&key).as_ref(), ring.get_node(&key));
+        }
+        assert_eq!(HashRing::<String>::new().get_node_cloned(&"key"), None);
+    }
+
+    /// Also run without the `std` feature, where the ring is built on
+    /// `alloc` alone.
+    #[test]
+    fn routes_with_a_custom_hasher_on_alloc_alone() {
+        let mut ring = HashRing::with_hasher(Identity::default());
+        ring.add_nodes([100u64, 200, 300]);
+        assert_eq!(ring.get_node(&150u64), Some(&200));
+        assert_eq!(ring.get_node(&300u64), Some(&300));
+        assert_eq!(ring.get_node(&301u64), Some(&100));
+    }
+
+    #[test]
+    fn get_nodes_with_positions_reports_where_each_node_was_met() {
+        let mut ring = HashRing::with_replicas_and_hasher(2, Identity::default());
+        ring.add_nodes([100u64, 200, 300]);
+
+        // The points are at 100, 101, 200, 201, 300 and 301.
+        assert_eq!(
+            ring.get_nodes_with_positions(&250u64, 3),
+            [(&300, 300), (&100, 100), (&200, 200)]
+        );
+        assert_eq!(
+            ring.get_nodes_with_positions(&101u64, 2),
+            [(&100, 101), (&200, 200)]
+        );
+        assert_eq!(ring.get_nodes_with_positions(&302u64, 1), [(&100, 100)]);
+    }
+
+    #[test]
+    fn keys_affected_by_remove_are_exactly_the_removed_nodes_keys() {
+        let mut ring = HashRing::with_replicas(32);
+        ring.add_nodes(0..6u32);
+        let keys = sample_keys(28, 5_000);
+
+        let affected = ring.keys_affected_by_remove(&2, &keys);
+        let mut shrunk = ring.clone();
+        shrunk.remove_node(&2);
+
+        let owned = ring.keys_owned_by(&2, &keys);
+        assert!(!owned.is_empty());
+        assert!(affected.iter().map(|&(key, _)| key).eq(owned));
+        for &(key, to) in &affected {
+            assert_eq!(shrunk.get_node(key), Some(to));
+        }
+        for key in &keys {
+            if ring.get_node(key) != Some(&2) {
+                assert_eq!(ring.get_node(key), shrunk.get_node(key));
+            }
+        }
+    }
+
+    #[test]
+    fn ring_points_are_sorted_and_count_every_point() {
+        let mut ring = HashRing::with_replicas(16);
+        ring.add_nodes(["a", "b", "c"]);
+        ring.add_weighted_node("d", 2);
+
+        let points = ring.ring_points();
+        assert_eq!(points.len(), 16 * 5);
+        assert!(points.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert!(points
+            .iter()
+            .map(|&(at, node)| (at, node))
+            .eq(ring.iter().map(|(&at, node)| (at, node))));
+    }
+
+    #[test]
+    fn equality_ignores_insertion_order() {
+        let mut forward = HashRing::with_replicas(8);
+        forward.add_nodes(["a", "b", "c"]);
+        let mut backward = HashRing::with_replicas(8);
+        backward.add_nodes(["c", "b", "a"]);
+        assert_eq!(forward, backward);
+
+        backward.remove_node(&"b");
+        assert_ne!(forward, backward);
+    }
+
+    #[test]
+    fn get_node_by_bytes_hashes_just_the_bytes() {
+        let mut ring = HashRing::with_replicas(64);
+        ring.add_nodes(["a", "b", "c"]);
+
+        // A key whose `Hash` impl writes only its bytes routes the same.
+        assert_eq!(
+            ring.get_node_by_bytes(b"hello"),
+            ring.get_node(&RawBytes(b"hello"))
+        );
+        // `str` also writes a terminator, so the hash differs; which node
+        // the two land on may still agree by chance.
+        assert_ne!(ring.hash_one(&RawBytes(b"hello")), ring.hash_one("hello"));
+    }
+
+    #[test]
+    fn hash_one_depends_only_on_its_input() {
+        let ring = HashRing::<&str>::new();
+        let first = ring.hash_one("key");
+        ring.hash_one(&12345u64);
+        assert_eq!(ring.hash_one("key"), first);
+        assert_eq!(HashRing::<u32>::new().hash_one("key"), first);
+        assert_ne!(ring.hash_one("other key"), first);
+    }
+
+    #[test]
+    fn get_node_range_reports_the_arc_between_neighbouring_points() {
+        let mut ring = HashRing::with_hasher(Identity::default());
+        ring.add_nodes([100u64, 200, 300]);
+
+        assert_eq!(ring.get_node_range(&150u64), Some((&200, 101..=200)));
+        assert_eq!(ring.get_node_range(&200u64), Some((&200, 101..=200)));
+        assert_eq!(
+            ring.get_node_range(&50u64),
+            Some((&100, RangeInclusive::new(301, 100)))
+        );
+        assert_eq!(
+            ring.get_node_range(&350u64),
+            Some((&100, RangeInclusive::new(301, 100)))
+        );
+
+        ring.remove_nodes(&[200, 300]);
+        assert_eq!(
+            ring.get_node_range(&7u64),
+            Some((&100, RangeInclusive::new(101, 100)))
+        );
+        ring.clear();
+        assert_eq!(ring.get_node_range(&7u64), None);
+    }
+
+    #[test]
+    fn insert_node_reports_duplicates_and_collisions() {
+        let mut ring = HashRing::with_replicas(4);
+        assert!(ring.insert_node("a"));
+        assert!(!ring.insert_node("a"));
+        assert_eq!(ring.len(), 1);
+        assert_eq!(ring.iter().len(), 4);
+
+        // A node already there keeps its weight and points.
+        ring.add_weighted_node("b", 3);
+        let before = ring.clone();
+        assert!(!ring.insert_node("b"));
+        assert_eq!(ring.topology(), [("a", 1), ("b", 3)]);
+        assert_eq!(ring.iter().len(), 16);
+        assert_eq!(ring, before);
+
+        let mut colliding = HashRing::with_hasher(Constant::default());
+        assert!(colliding.insert_node("a"));
+        assert!(!colliding.insert_node("b"));
+        assert_eq!(colliding.len(), 2);
+    }
+
+    #[test]
+    fn colliding_nodes_are_kept_and_take_over_in_turn() {
+        let mut ring = HashRing::with_hasher(Constant::default());
+        ring.add_nodes(["c", "a", "b"]);
+        assert_eq!(ring.len(), 3);
+        assert!(ring.check_invariants().is_ok());
+
+        // The smallest node owns the shared position whatever the order.
+        assert_eq!(ring.get_node(&"key"), Some(&"a"));
+        ring.remove_node(&"a");
+        assert_eq!(ring.get_node(&"key"), Some(&"b"));
+        ring.remove_node(&"b");
+        assert_eq!(ring.get_node(&"key"), Some(&"c"));
+        ring.remove_node(&"c");
+        assert_eq!(ring.get_node(&"key"), None);
+    }
+
+    #[test]
+    fn owner_of_position_takes_the_point_at_or_after_it() {
+        let mut ring = HashRing::with_hasher(Identity::default());
+        ring.add_nodes([100u64, 200, 300]);
+
+        assert_eq!(ring.owner_of_position(0), Some(&100));
+        assert_eq!(ring.owner_of_position(100), Some(&100));
+        assert_eq!(ring.owner_of_position(101), Some(&200));
+        assert_eq!(ring.owner_of_position(250), Some(&300));
+        assert_eq!(ring.owner_of_position(301), Some(&100));
+        assert_eq!(ring.owner_of_position(u64::MAX), Some(&100));
+        assert_eq!(HashRing::<u64>::new().owner_of_position(5), None);
+    }
+
+    #[test]
+    fn migration_plan_pairs_each_key_with_both_owners() {
+        let mut old = HashRing::with_replicas(32);
+        old.add_nodes(["a", "b", "c"]);
+        let mut new = old.clone();
+        new.add_node("d");
+        let keys = sample_keys(40, 2_000);
+
+        let plan = HashRing::migration_plan(&old, &new, &keys);
+        assert_eq!(plan.len(), keys.len());
+        let mut moved = 0;
+        for (key, from, to) in plan {
+            assert_eq!((from, to), (old.get_node(key), new.get_node(key)));
+            if from != to {
+                moved += 1;
+                assert_eq!(to, Some(&"d"));
+            }
+        }
+        assert!(moved > 0);
+    }
+
+    #[test]
+    fn get_shard_is_the_owning_points_index() {
+        let mut ring = HashRing::with_replicas(8);
+        ring.add_nodes(["a", "b", "c"]);
+        let points = ring.ring_points();
+        for key in sample_keys(41, 200) {
+            let shard = ring.get_shard(&key).unwrap();
+            assert_eq!(Some(points[shard].1), ring.get_node(&key));
+            assert_eq!(
+                Some(points[shard].0),
+                ring.successor(ring.hash_one(&key)).map(|(at, _)| at)
+            );
+        }
+        assert_eq!(HashRing::<&str>::new().get_shard(&1u64), None);
+    }
+
+    #[test]
+    fn remove_node_drops_every_replica() {
+        let mut ring = HashRing::with_replicas(100);
+        ring.add_nodes(["a", "b"]);
+        assert_eq!(ring.iter().len(), 200);
+        ring.remove_node(&"a");
+        assert_eq!(ring.iter().len(), 100);
+        assert!(ring.iter().all(|(_, &node)| node == "b"));
+    }
+
+    #[test]
+    fn extend_matches_adding_one_at_a_time() {
+        let mut extended = HashRing::with_replicas(4);
+        extended.add_nodes(["a", "b"]);
+        let mut looped = extended.clone();
+
+        extended.extend(["c", "d"]);
+        looped.add_node("c");
+        looped.add_node("d");
+        assert_eq!(extended, looped);
+        assert!(extended.nodes().eq(&["a", "b", "c", "d"]));
+    }
+
+    #[test]
+    fn from_topology_rebuilds_the_ring_topology_describes() {
+        let mut unweighted = HashRing::with_replicas(16);
+        unweighted.add_nodes(["a", "b", "c"]);
+        let mut weighted = unweighted.clone();
+        weighted.add_weighted_node("d", 3);
+        weighted.set_weight(&"a", 2);
+
+        let keys = sample_keys(46, 1_000);
+        for ring in [unweighted, weighted] {
+            let rebuilt = HashRing::from_topology(ring.topology(), ring.replicas());
+            assert_eq!(rebuilt, ring);
+            assert_eq!(rebuilt.topology(), ring.topology());
+            assert!(keys
+                .iter()
+                .all(|key| rebuilt.get_node(key) == ring.get_node(key)));
+        }
+    }
+
+    #[test]
+    fn imbalance_factor_grows_as_the_ring_gets_lopsided() {
+        let nodes = ["a", "b", "c", "d"];
+        let even: HashRing<_> = HashRing::builder().replicas(200).nodes(nodes).build();
+        let lumpy: HashRing<_> = HashRing::builder().replicas(1).nodes(nodes).build();
+
+        assert!((1.0..1.25).contains(&even.imbalance_factor()));
+        assert!(lumpy.imbalance_factor() > even.imbalance_factor());
+        assert_eq!(HashRing::<&str>::new().imbalance_factor(), 1.0);
+
+        let mut halves = HashRing::with_hasher(Identity::default());
+        halves.add_node_at("a", 0);
+        halves.add_node_at("b", 1 << 63);
+        assert_eq!(halves.imbalance_factor(), 1.0);
+        halves.add_node_at("c", 1 << 62);
+        assert_eq!(halves.imbalance_factor(), 1.5);
+    }
+
+    #[test]
+    fn replica_key_decides_where_each_point_goes() {
+        // The identity hasher turns eight big-endian bytes back into the
+        // number they encode, so replica `r` of node `n` lands at `n * 100 + r`.
+        let ring = HashRing::builder()
+            .hasher(Identity::default())
+            .replicas(3)
+            .replica_key(|node: &u64, replica| (node * 100 + replica as u64).to_be_bytes().to_vec())
+            .nodes([1u64, 2])
+            .build();
+
+        let points: Vec<(u64, u64)> = ring.iter().map(|(&at, &node)| (at, node)).collect();
+        assert_eq!(
+            points,
+            [(100, 1), (101, 1), (102, 1), (200, 2), (201, 2), (202, 2)]
+        );
+        assert_eq!(ring.get_node(&150u64), Some(&2));
+    }
+
+    #[test]
+    fn get_nodes_for_keys_routes_each_key_as_get_node_does() {
+        let ring: HashRing<_> = HashRing::builder()
+            .replicas(8)
+            .nodes(["a", "b", "c"])
+            .build();
+        let keys = sample_keys(50, 200);
+
+        let routed = ring.get_nodes_for_keys(&keys);
+        assert_eq!(routed.len(), keys.len());
+        for ((key, node), expected) in routed.into_iter().zip(&keys) {
+            assert!(core::ptr::eq(key, expected));
+            assert_eq!(node, ring.get_node(key));
+        }
+        assert!(HashRing::<&str>::new()
+            .get_nodes_for_keys(&keys)
+            .iter()
+            .all(|(_, node)| node.is_none()));
+    }
+
+    #[test]
+    fn successor_and_predecessor_wrap_at_both_ends() {
+        let mut ring = HashRing::with_hasher(Identity::default());
+        ring.add_nodes([100u64, 200, 300]);
+
+        assert_eq!(ring.successor(150), Some((200, &200)));
+        assert_eq!(ring.successor(200), Some((200, &200)));
+        assert_eq!(ring.successor(301), Some((100, &100)));
+        assert_eq!(ring.successor(u64::MAX), Some((100, &100)));
+        assert_eq!(ring.successor(0), Some((100, &100)));
+
+        assert_eq!(ring.predecessor(150), Some((100, &100)));
+        assert_eq!(ring.predecessor(200), Some((200, &200)));
+        assert_eq!(ring.predecessor(99), Some((300, &300)));
+        assert_eq!(ring.predecessor(0), Some((300, &300)));
+        assert_eq!(ring.predecessor(u64::MAX), Some((300, &300)));
+
+        ring.clear();
+        assert_eq!(ring.successor(0), None);
+        assert_eq!(ring.predecessor(0), None);
+    }
+
+    #[test]
+    fn replace_node_hands_over_its_points_and_nothing_else() {
+        let ring: HashRing<_> = HashRing::builder()
+            .replicas(16)
+            .nodes(["a", "b", "c"])
+            .build();
+        let mut replaced = ring.clone();
+        replaced.replace_node(&"b", "d");
+
+        assert!(!replaced.contains_node(&"b") && replaced.contains_node(&"d"));
+        assert!(ring
+            .iter()
+            .map(|(&at, &node)| (at, if node == "b" { "d" } else { node }))
+            .eq(replaced.iter().map(|(&at, &node)| (at, node))));
+        for key in sample_keys(53, 1_000) {
+            let expected = match ring.get_node(&key) {
+                Some(&"b") => Some(&"d"),
+                other => other,
+            };
+            assert_eq!(replaced.get_node(&key), expected);
+        }
+    }
+
+    #[test]
+    fn ascii_ring_draws_a_labelled_row_per_node() {
+        let mut ring = HashRing::with_hasher(Identity::default());
+        ring.add_node_at(1u64, 0);
+        ring.add_node_at(2, 1 << 63);
+        ring.add_node_at(2, u64::MAX);
+
+        assert_eq!(ring.ascii_ring(8), "|------- 1\n----|--| 2\n");
+        let drawing = ring.ascii_ring(40);
+        for (row, label) in drawing.lines().zip(["1", "2"]) {
+            assert_eq!(row.len(), 40 + 1 + label.len());
+            assert!(row.ends_with(&format!(" {label}")));
+        }
+        assert_eq!(ring.ascii_ring(0), " 1\n 2\n");
+    }
+
+    #[test]
+    fn get_node_or_falls_back_only_when_the_ring_is_empty() {
+        let fallback = "fallback";
+        let mut ring = HashRing::new();
+        assert_eq!(ring.get_node_or("key", &fallback), &"fallback");
+        assert_eq!(ring.get_node_or_else("key", || &fallback), &"fallback");
+
+        ring.add_nodes(["a", "b"]);
+        let owner = ring.get_node("key").unwrap();
+        assert_eq!(ring.get_node_or("key", &fallback), owner);
+        assert_eq!(
+            ring.get_node_or_else("key", || unreachable!("the ring has nodes")),
+            owner
+        );
+    }
+
+    #[test]
+    fn indexing_returns_the_node_get_node_does() {
+        let ring: HashRing<_> = HashRing::builder()
+            .replicas(8)
+            .nodes(["a", "b", "c"])
+            .build();
+        for key in sample_keys(57, 100) {
+            assert_eq!(Some(&ring[&key]), ring.get_node(&key));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot look up a key on a hash ring with no nodes")]
+    fn indexing_an_empty_ring_panics() {
+        let _ = HashRing::<&str>::new()["key"];
+    }
+
+    #[test]
+    fn key_histogram_counts_the_keys_each_node_owns() {
+        let mut ring = HashRing::with_hasher(Identity::default());
+        ring.add_nodes([100u64, 200, 300]);
+
+        let keys = [5u64, 100, 150, 199, 250, 301, 7, 7];
+        let expected = BTreeMap::from([(100, 5), (200, 2), (300, 1)]);
+        assert_eq!(ring.key_histogram(&keys), expected);
+        assert_eq!(ring.key_histogram(&[150u64]), BTreeMap::from([(200, 1)]));
+        assert!(HashRing::<u64>::new().key_histogram(&keys).is_empty());
+    }
+
+    #[test]
+    fn shrink_to_fit_trims_the_lists_left_by_bulk_removals() {
+        let mut ring = HashRing::with_replicas_and_hasher(1, Constant::default());
+        ring.add_nodes(0u32..10_000);
+        let removed: Vec<u32> = (0..9_900).collect();
+        ring.remove_nodes(&removed);
+        let waiting_capacity = |ring: &HashRing<u32, Constant>| -> usize {
+            ring.collisions.values().map(Vec::capacity).sum()
+        };
+
+        assert_eq!(ring.len(), 100);
+        assert!(waiting_capacity(&ring) >= 9_999);
+        ring.shrink_to_fit();
+        assert_eq!(waiting_capacity(&ring), 99);
+        assert_eq!(ring.get_node("key"), Some(&9_900));
+    }
+
+    #[test]
+    fn get_node_excluding_walks_past_the_excluded_nodes() {
+        let mut ring = HashRing::with_hasher(Identity::default());
+        ring.add_nodes([100u64, 200, 300]);
+
+        assert_eq!(ring.get_node_excluding(&150u64, &[]), Some(&200));
+        assert_eq!(ring.get_node_excluding(&150u64, &[200]), Some(&300));
+        assert_eq!(ring.get_node_excluding(&250u64, &[300]), Some(&100));
+        assert_eq!(ring.get_node_excluding(&250u64, &[300, 100]), Some(&200));
+        assert_eq!(ring.get_node_excluding(&250u64, &[100, 200, 300]), None);
+    }
+
+    #[test]
+    fn into_nodes_returns_each_node_once() {
+        let mut ring = HashRing::with_replicas(16);
+        ring.add_nodes([String::from("c"), String::from("a"), String::from("b")]);
+        ring.add_weighted_node(String::from("d"), 3);
+
+        assert_eq!(ring.into_nodes(), ["a", "b", "c", "d"]);
+        assert!(HashRing::<String>::new().into_nodes().is_empty());
+    }
+
+    #[test]
+    fn check_invariants_passes_a_sound_ring_and_names_what_broke() {
+        let mut ring = HashRing::with_replicas(4);
+        ring.add_nodes(["a", "b", "c"]);
+        ring.remove_node(&"b");
+        assert_eq!(ring.check_invariants(), Ok(()));
+
+        let (&position, &owner) = ring.keys.iter().next().unwrap();
+        let mut missing_point = ring.clone();
+        missing_point.keys.remove(&position);
+        assert_eq!(
+            missing_point.check_invariants(),
+            Err(format!("{owner:?} has no point at {position:?}"))
+        );
+
+        let mut unlisted_point = ring.clone();
+        unlisted_point.keys.insert(position.wrapping_add(1), "a");
+        assert_eq!(
+            unlisted_point.check_invariants(),
+            Err(format!(
+                "the point at {:?} belongs to \"a\", which does not list it",
+                position.wrapping_add(1)
+            ))
+        );
+
+        let mut stray_load = ring.clone();
+        stray_load.loads.insert("b", 1);
+        assert_eq!(
+            stray_load.check_invariants(),
+            Err(String::from("\"b\" carries load but is not on the ring"))
+        );
+    }
+
+    /// A node carrying state its `Hash`, `Eq` and `Ord` impls ignore.
+    #[derive(Debug, Clone)]
+    struct Backend {
+        name: &'static str,
+        requests: u32,
+    }
+
+    impl Hash for Backend {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            self.name.hash(state);
+        }
+    }
+
+    impl PartialEq for Backend {
+        fn eq(&self, other: &Self) -> bool {
+            self.name == other.name
+        }
+    }
+
+    impl Eq for Backend {}
+
+    impl PartialOrd for Backend {
+        fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Backend {
+        fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+            self.name.cmp(other.name)
+        }
+    }
+
+    #[test]
+    fn get_node_mut_changes_are_seen_by_the_next_lookup() {
+        let mut ring = HashRing::with_replicas(8);
+        ring.add_nodes(["a", "b", "c"].map(|name| Backend { name, requests: 0 }));
+        let owner = ring.get_node("key").unwrap().name;
+
+        for _ in 0..3 {
+            ring.get_node_mut("key").unwrap().requests += 1;
+        }
+        let node = ring.get_node("key").unwrap();
+        assert_eq!((node.name, node.requests), (owner, 3));
+        assert!(HashRing::<Backend>::new().get_node_mut("key").is_none());
+    }
+
+    #[test]
+    fn unhealthy_nodes_hand_their_keys_to_the_next_healthy_one() {
+        let mut ring = HashRing::with_hasher(Identity::default());
+        ring.add_nodes([100u64, 200, 300]);
+        ring.set_health(|&node| node != 200);
+
+        assert_eq!(ring.get_node(&150u64), Some(&300));
+        assert_eq!(ring.get_node(&50u64), Some(&100));
+        assert_eq!(ring.get_node(&250u64), Some(&300));
+
+        ring.set_health(|&node| node == 100);
+        assert_eq!(ring.get_node(&250u64), Some(&100));
+        ring.set_health(|_| false);
+        assert_eq!(ring.get_node(&250u64), None);
+        ring.clear_health();
+        assert_eq!(ring.get_node(&150u64), Some(&200));
+    }
+
+    #[test]
+    fn remove_nodes_matches_removing_one_at_a_time() {
+        let mut ring: HashRing<_> = HashRing::builder()
+            .replicas(8)
+            .nodes(["a", "b", "c", "d", "e"])
+            .build();
+        let mut looped = ring.clone();
+
+        ring.remove_nodes(&["b", "d", "z"]);
+        looped.remove_node(&"b");
+        looped.remove_node(&"d");
+        assert_eq!(ring, looped);
+        assert!(ring.nodes().eq(&["a", "c", "e"]));
+        for key in sample_keys(67, 300) {
+            assert!(matches!(ring.get_node(&key), Some(&("a" | "c" | "e"))));
+        }
+    }
+
+    #[test]
+    fn get_primary_and_replicas_splits_get_nodes() {
+        let ring: HashRing<_> = HashRing::builder()
+            .replicas(8)
+            .nodes(["a", "b", "c", "d"])
+            .build();
+        for key in sample_keys(68, 100) {
+            let set = ring.get_primary_and_replicas(&key, 2).unwrap();
+            assert_eq!(Some(set.primary), ring.get_node(&key));
+            assert_eq!(set.replicas, ring.get_nodes(&key, 3)[1..]);
+            assert!(!set.replicas.contains(&set.primary));
+        }
+
+        let everyone = ring.get_primary_and_replicas("key", 10).unwrap();
+        assert_eq!(everyone.replicas.len(), 3);
+        assert_eq!(
+            HashRing::<&str>::new().get_primary_and_replicas("key", 2),
+            None
+        );
+    }
+
+    #[test]
+    fn a_ring_is_built_without_naming_a_key_type() {
+        let mut ring = HashRing::<u16>::new();
+        ring.add_nodes([8080, 8081, 8082]);
+
+        assert!(ring.get_node("a string key").is_some());
+        assert!(ring.get_node(&42u64).is_some());
+        assert_eq!(
+            ring.get_node("a string key"),
+            ring.get_node(&String::from("a string key"))
+        );
+    }
+
+    #[test]
+    fn nodes_clockwise_from_lists_every_node_starting_at_the_owner() {
+        let mut ring = HashRing::with_hasher(Identity::default());
+        ring.add_nodes([100u64, 200, 300]);
+        assert_eq!(ring.nodes_clockwise_from(&250u64), [&300, &100, &200]);
+
+        let ring: HashRing<_> = HashRing::builder()
+            .replicas(8)
+            .nodes(["a", "b", "c", "d"])
+            .build();
+        for key in sample_keys(71, 100) {
+            let order = ring.nodes_clockwise_from(&key);
+            assert_eq!(order.len(), ring.len());
+            assert_eq!(order.first().copied(), ring.get_node(&key));
+            assert_eq!(order, ring.get_nodes(&key, usize::MAX));
+        }
+    }
+
+    #[test]
+    fn shard_table_has_a_slot_per_shard_owned_by_its_middle() {
+        let ring: HashRing<_> = HashRing::builder()
+            .replicas(8)
+            .nodes(["a", "b", "c"])
+            .build();
+        let table = ring.shard_table(1024);
+        assert_eq!(table.len(), 1024);
+        for (slot, &node) in table.iter().enumerate() {
+            let middle = (2 * slot as u64 + 1) << (64 - 10 - 1);
+            assert_eq!(ring.owner_of_position(middle), Some(node));
+        }
+
+        let agreeing = sample_keys(74, 2_000)
+            .iter()
+            .filter(|key| {
+                Some(table[(ring.hash_one(key) >> (64 - 10)) as usize]) == ring.get_node(key)
+            })
+            .count();
+        assert!(agreeing > 1_900, "{agreeing}");
+        assert!(HashRing::<&str>::new().shard_table(16).is_empty());
+    }
+
+    #[test]
+    fn composite_keys_route_by_both_parts() {
+        let ring: HashRing<_> = HashRing::builder()
+            .replicas(8)
+            .nodes(["a", "b", "c", "d"])
+            .build();
+        let owner = ring.get_node_composite(&"tenant-1", &"key");
+        assert_eq!(ring.get_node_composite(&"tenant-1", &"key"), owner);
+        assert_eq!(ring.get_node(&("tenant-1", "key")), owner);
+
+        let spread: BTreeSet<_> = (0..50)
+            .map(|tenant| ring.get_node_composite(&tenant, &"key"))
+            .collect();
+        assert!(spread.len() > 1);
+    }
+
+    #[test]
+    fn remove_node_returns_the_node_only_if_it_was_there() {
+        let mut ring = HashRing::with_replicas(16);
+        ring.add_nodes([String::from("a"), String::from("b")]);
+
+        assert_eq!(
+            ring.remove_node(&String::from("a")),
+            Some(String::from("a"))
+        );
+        assert_eq!(ring.remove_node(&String::from("a")), None);
+        assert_eq!(ring.remove_node(&String::from("z")), None);
+        assert_eq!(ring.iter().len(), 16);
+    }
+
+    /// Returns `items` in an order drawn from `seed` by a Fisher-Yates
+    /// shuffle over [`sample_keys`].
+    fn shuffled<I: Clone>(items: &[I], seed: u64) -> Vec<I> {
+        let mut items = items.to_vec();
+        let draws = sample_keys(seed, items.len());
+        for i in (1..items.len()).rev() {
+            items.swap(i, (draws[i] % (i as u64 + 1)) as usize);
+        }
+        items
+    }
+
+    #[test]
+    fn insertion_order_never_changes_placement() {
+        // With the identity hasher, replica `r` of node `n` sits at `n + r`,
+        // so neighbouring nodes collide on almost every point.
+        let nodes: Vec<u64> = (0..40).map(|n| n * 3).collect();
+        let keys: Vec<u64> = sample_keys(77, 500).iter().map(|key| key % 150).collect();
+        let build = |order: &[u64]| {
+            let mut ring = HashRing::with_replicas_and_hasher(4, Identity::default());
+            ring.add_nodes(order.iter().copied());
+            ring
+        };
+
+        let reference = build(&nodes);
+        assert!(!reference.collisions.is_empty());
+        for seed in 0..20 {
+            let ring = build(&shuffled(&nodes, seed));
+            assert_eq!(ring, reference);
+            assert_eq!(ring.check_invariants(), Ok(()));
+            for key in &keys {
+                assert_eq!(ring.get_node(key), reference.get_node(key));
+            }
+        }
+
+        let mut everything_collides = HashRing::with_hasher(Constant::default());
+        everything_collides.add_nodes(shuffled(&nodes, 99));
+        assert_eq!(everything_collides.get_node("key"), Some(&0));
+    }
+
+    /// A set of up to 40 nodes from a range narrow enough that, with the
+    /// identity hasher, their points collide often, together with the same
+    /// nodes in an arbitrary order.
+    #[cfg(feature = "std")]
+    fn nodes_and_an_order() -> impl proptest::strategy::Strategy<Value = (Vec<u64>, Vec<u64>)> {
+        use proptest::prelude::*;
+
+        proptest::collection::btree_set(0u64..120, 1..40).prop_flat_map(|nodes| {
+            let nodes: Vec<u64> = nodes.into_iter().collect();
+            (Just(nodes.clone()), Just(nodes).prop_shuffle())
+        })
+    }
+
+    #[cfg(feature = "std")]
+    proptest::proptest! {
+        #[test]
+        fn any_insertion_order_gives_the_same_placement(
+            (nodes, order) in nodes_and_an_order(),
+            replicas in 1usize..6,
+            keys in proptest::collection::vec(0u64..130, 1..100),
+            wide_keys in proptest::collection::vec(proptest::num::u64::ANY, 0..20),
+        ) {
+            let build = |order: &[u64]| {
+                let mut ring = HashRing::with_replicas_and_hasher(replicas, Identity::default());
+                ring.add_nodes(order.iter().copied());
+                ring
+            };
+            let (reference, ring) = (build(&nodes), build(&order));
+            proptest::prop_assert_eq!(&ring, &reference);
+            proptest::prop_assert_eq!(ring.check_invariants(), Ok(()));
+            for key in keys.iter().chain(&wide_keys) {
+                proptest::prop_assert_eq!(ring.get_node(key), reference.get_node(key));
+            }
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn rehash_with_moves_a_ring_to_another_hasher_type() {
+        let mut ring = HashRing::with_replicas_and_hasher(16, StdHashBuilder::default());
+        ring.add_nodes(["a", "b", "c"]);
+        ring.add_weighted_node("d", 2);
+        ring.drain_node(&"b");
+        let before = ring
+            .ring_points()
+            .into_iter()
+            .map(|(at, &node)| (at, node))
+            .collect::<Vec<_>>();
+
+        let rehashed: HashRing<&str, FixedHasher> = ring.rehash_with(FixedHasher::with_seed(78));
+        let mut fresh = HashRing::with_replicas_and_hasher(16, FixedHasher::with_seed(78));
+        fresh.add_nodes(["a", "b", "c"]);
+        fresh.add_weighted_node("d", 2);
+        assert_eq!(rehashed, fresh);
+        assert!(rehashed.nodes().eq(&["a", "b", "c", "d"]));
+        assert!(rehashed.is_draining(&"b"));
+        assert_eq!(rehashed.topology(), fresh.topology());
+
+        let moved = before
+            .iter()
+            .filter(|&&(at, node)| rehashed.owner_of_position(at) != Some(&node))
+            .count();
+        assert!(moved > 0);
+    }
+
+    #[test]
+    fn nodes_in_range_lists_the_owners_of_every_position_in_it() {
+        // Node 100 owns 301..=100 wrapping, 200 owns 101..=200 and 300 owns
+        // 201..=300.
+        let mut ring = HashRing::with_hasher(Identity::default());
+        ring.add_nodes([100u64, 200, 300]);
+
+        assert_eq!(ring.nodes_in_range(120, 180), [&200]);
+        assert_eq!(ring.nodes_in_range(101, 200), [&200]);
+        assert_eq!(ring.nodes_in_range(100, 201), [&100, &200, &300]);
+        assert_eq!(ring.nodes_in_range(250, 350), [&300, &100]);
+        assert_eq!(ring.nodes_in_range(350, 150), [&100, &200]);
+        assert_eq!(ring.nodes_in_range(350, 50), [&100]);
+        assert_eq!(ring.nodes_in_range(0, u64::MAX), [&100, &200, &300]);
+        assert!(HashRing::<u64>::new().nodes_in_range(0, 10).is_empty());
+    }
+
+    #[test]
+    fn display_reports_the_node_and_point_counts() {
+        let mut ring = HashRing::with_replicas(128);
+        ring.add_nodes(["c", "a", "b"]);
+        assert_eq!(format!("{ring}"), "HashRing(3 nodes, 384 points): a, b, c");
+
+        let mut single = HashRing::new();
+        single.add_node("a");
+        assert_eq!(format!("{single}"), "HashRing(1 node, 1 point): a");
+        assert_eq!(
+            format!("{}", HashRing::<&str>::new()),
+            "HashRing(0 nodes, 0 points)"
+        );
+    }
+
+    #[test]
+    fn a_strong_bias_wins_among_the_nearby_candidates() {
+        let ring: HashRing<_> = HashRing::builder()
+            .replicas(8)
+            .nodes(["a", "b", "c", "d", "e"])
+            .build();
+        let mut favoured = 0;
+        for key in sample_keys(82, 500) {
+            assert_eq!(ring.get_node_biased(&key, |_| 0.0), ring.get_node(&key));
+
+            let candidates = ring.get_nodes(&key, CANDIDATE_NODES);
+            let chosen = ring.get_node_biased(&key, |&node| if node == "c" { 1e9 } else { 0.0 });
+            if candidates.contains(&&"c") {
+                assert_eq!(chosen, Some(&"c"));
+                favoured += 1;
+            } else {
+                assert_eq!(chosen, ring.get_node(&key));
+            }
+        }
+        assert!(favoured > 200, "{favoured}");
+    }
+
+    #[test]
+    fn keys_owned_by_splits_the_sample_between_the_nodes() {
+        let ring: HashRing<_> = HashRing::builder()
+            .replicas(8)
+            .nodes(["a", "b", "c"])
+            .build();
+        let keys = sample_keys(83, 300);
+
+        let mut total = 0;
+        for node in ["a", "b", "c"] {
+            let owned = ring.keys_owned_by(&node, &keys);
+            assert!(owned.iter().all(|key| ring.get_node(key) == Some(&node)));
+            total += owned.len();
+        }
+        assert_eq!(total, keys.len());
+        assert!(ring.keys_owned_by(&"z", &keys).is_empty());
+    }
+
+    #[test]
+    fn from_vec_keeps_every_colliding_node() {
+        // The identity hasher sums a pair's halves, so the first three nodes
+        // all land on position 3.
+        let nodes = vec![(1u64, 2u64), (2, 1), (0, 3), (10, 0)];
+        let mut ring: HashRing<(u64, u64), Identity> = HashRing::from(nodes.clone());
+
+        assert_eq!(ring.len(), nodes.len());
+        assert!(nodes.iter().all(|node| ring.contains_node(node)));
+        assert_eq!(ring.check_invariants(), Ok(()));
+        assert_eq!(ring.get_node(&7u64), Some(&(10, 0)));
+        for owner in [(0, 3), (1, 2), (2, 1)] {
+            assert_eq!(ring.get_node(&3u64), Some(&owner));
+            ring.remove_node(&owner);
+        }
+        assert_eq!(ring.get_node(&3u64), Some(&(10, 0)));
+    }
+
+    #[test]
+    fn get_nodes_wraps_in_clockwise_order_without_repeats() {
+        // Each node `n` has points at `n` and `n + 1`.
+        let top = u64::MAX - 10;
+        let mut ring = HashRing::with_replicas_and_hasher(2, Identity::default());
+        ring.add_nodes([30u64, top, 10, 20]);
+
+        assert_eq!(ring.get_nodes(&(top - 10), 4), [&top, &10, &20, &30]);
+        assert_eq!(ring.get_nodes(&(top + 1), 4), [&top, &10, &20, &30]);
+        assert_eq!(ring.get_nodes(&(top - 10), 2), [&top, &10]);
+        assert_eq!(ring.get_nodes(&u64::MAX, 10), [&10, &20, &30, &top]);
+        assert_eq!(ring.get_nodes(&25u64, 4), [&30, &top, &10, &20]);
+    }
+
+    #[test]
+    fn get_node_with_distance_is_the_gap_to_the_owning_point() {
+        let mut ring = HashRing::with_hasher(Identity::default());
+        ring.add_nodes([100u64, 200, 300]);
+
+        assert_eq!(ring.get_node_with_distance(&150u64), Some((&200, 50)));
+        assert_eq!(ring.get_node_with_distance(&200u64), Some((&200, 0)));
+        assert_eq!(
+            ring.get_node_with_distance(&301u64),
+            Some((&100, u64::MAX - 200))
+        );
+        assert_eq!(ring.get_node_with_distance(&0u64), Some((&100, 100)));
+        assert_eq!(HashRing::<u64>::new().get_node_with_distance(&0u64), None);
+    }
+
+    #[test]
+    fn failover_chain_starts_with_the_owner_and_lists_every_node() {
+        let mut ring = HashRing::with_hasher(Identity::default());
+        ring.add_nodes([100u64, 200, 300, 400]);
+        assert_eq!(ring.failover_chain(&150u64), [&200, &300, &400, &100]);
+
+        ring.set_health(|&node| node != 200 && node != 400);
+        assert_eq!(ring.get_node(&150u64), Some(&300));
+        assert_eq!(ring.failover_chain(&150u64), [&300, &100, &200, &400]);
+        assert!(HashRing::<u64>::new().failover_chain(&150u64).is_empty());
+    }
+
+    #[test]
+    fn rings_differing_only_by_salt_place_keys_independently() {
+        let nodes = ["a", "b", "c", "d"];
+        let build = |salt| -> HashRing<&str> {
+            HashRing::builder()
+                .replicas(8)
+                .salt(salt)
+                .nodes(nodes)
+                .build()
+        };
+        let (first, second) = (build(1), build(2));
+        let keys = sample_keys(92, 1_000);
+
+        let differing = keys
+            .iter()
+            .filter(|key| first.get_node(key) != second.get_node(key))
+            .count();
+        assert!(differing > 500, "{differing}");
+        for key in &keys {
+            assert_eq!(first.get_node(key), build(1).get_node(key));
+        }
+        assert_eq!(HashRing::with_salt(0), HashRing::<&str>::new());
+    }
+
+    #[test]
+    fn a_salted_ring_is_rebuilt_from_its_topology_through_the_builder() {
+        let ring: HashRing<_> = HashRing::builder()
+            .replicas(8)
+            .salt(92)
+            .nodes(["a", "b", "c"])
+            .build();
+
+        assert_ne!(
+            HashRing::from_topology(ring.topology(), ring.replicas()),
+            ring
+        );
+        let mut builder = HashRing::builder()
+            .replicas(ring.replicas())
+            .salt(ring.salt());
+        for (node, weight) in ring.topology() {
+            builder = builder.weighted_node(node, weight);
+        }
+        assert_eq!(builder.build(), ring);
+    }
+
+    #[test]
+    fn remove_node_reporting_lists_the_positions_it_vacated() {
+        let mut ring = HashRing::with_replicas(8);
+        ring.add_nodes(["a", "b", "c"]);
+        let before: BTreeSet<u64> = ring.iter().map(|(&at, _)| at).collect();
+
+        let vacated = ring.remove_node_reporting(&"b");
+        assert_eq!(vacated.len(), 8);
+        assert!(vacated.windows(2).all(|pair| pair[0] < pair[1]));
+        for position in &vacated {
+            assert!(before.contains(position));
+            assert!(ring.owner_of_position(*position) != Some(&"b"));
+            assert!(!ring.iter().any(|(at, _)| at == position));
+        }
+        assert_eq!(ring.iter().len(), 16);
+        assert!(ring.remove_node_reporting(&"b").is_empty());
+    }
+
+    #[test]
+    fn min_and_max_position_bound_the_points() {
+        let mut ring = HashRing::with_hasher(Identity::default());
+        assert_eq!((ring.min_position(), ring.max_position()), (None, None));
+
+        ring.add_nodes([200u64, 100, 300]);
+        assert_eq!(
+            (ring.min_position(), ring.max_position()),
+            (Some(100), Some(300))
+        );
+        ring.add_node(50);
+        assert_eq!(ring.min_position(), Some(50));
+        assert_eq!(ring.get_node(&301u64), Some(&50));
+    }
+
+    #[test]
+    fn adding_or_removing_a_node_never_moves_anyone_elses_points() {
+        let mut ring = HashRing::with_replicas(32);
+        ring.add_nodes(sample_keys(95, 50));
+        let positions_of = |ring: &HashRing<u64>| -> BTreeMap<u64, Vec<u64>> {
+            ring.nodes
+                .iter()
+                .map(|(&node, entry)| (node, entry.positions.clone()))
+                .collect()
+        };
+        let before = positions_of(&ring);
+
+        ring.add_node(1);
+        let after = positions_of(&ring);
+        assert_eq!(after.len(), before.len() + 1);
+        for (node, positions) in &before {
+            assert_eq!(&after[node], positions);
+        }
+        let added: BTreeSet<u64> = after[&1].iter().copied().collect();
+        for (&at, &node) in ring.iter() {
+            assert!(node == 1 || before[&node].contains(&at) && !added.contains(&at));
+        }
+
+        ring.remove_node(&1);
+        assert_eq!(positions_of(&ring), before);
+    }
+
+    #[test]
+    fn a_key_just_below_a_pinned_position_goes_to_the_pinned_node() {
+        let mut ring = HashRing::new();
+        ring.add_nodes(["a", "b", "c"]);
+        let unpinned = ring.clone();
+        let pinned = ring.hash_one("key").wrapping_add(1);
+        ring.add_node_at("pinned", pinned);
+
+        assert_eq!(ring.get_node("key"), Some(&"pinned"));
+        assert_eq!(ring.owner_of_position(pinned), Some(&"pinned"));
+        let past = pinned.wrapping_add(1);
+        assert_eq!(
+            ring.get_node_for_hash(past),
+            unpinned.get_node_for_hash(past)
+        );
+        assert_eq!(ring.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn placement_stability_measures_how_many_keys_stayed() {
+        let ring: HashRing<_> = HashRing::builder()
+            .replicas(64)
+            .nodes(["a", "b", "c", "d"])
+            .build();
+        assert_eq!(HashRing::placement_stability(&ring, &ring, 10_000), 1.0);
+        assert_eq!(HashRing::placement_stability(&ring, &ring, 0), 1.0);
+
+        let mut grown = ring.clone();
+        grown.add_node("e");
+        let stability = HashRing::placement_stability(&ring, &grown, 10_000);
+        assert!((0.75..0.85).contains(&stability), "{stability}");
+        assert_eq!(
+            stability,
+            HashRing::placement_stability(&ring, &grown, 10_000)
+        );
+    }
+
+    #[test]
+    fn try_from_nodes_accepts_clean_lists_and_names_conflicts() {
+        let ring = HashRing::<&str>::try_from_nodes(vec!["a", "b", "c"]).unwrap();
+        assert_eq!(ring, HashRing::from(vec!["a", "b", "c"]));
+
+        let duplicate = HashRing::<&str>::try_from_nodes(vec!["a", "b", "a"]).unwrap_err();
+        assert_eq!(duplicate, NodeConflict::Duplicate("a"));
+        assert_eq!(format!("{duplicate}"), "node \"a\" is listed twice");
+
+        let collision = HashRing::<&str, Constant>::try_from_nodes(vec!["a", "b"]).unwrap_err();
+        assert_eq!(
+            collision,
+            NodeConflict::Collision {
+                node: "b",
+                other: "a"
+            }
+        );
+        assert_eq!(
+            format!("{collision}"),
+            "a point of node \"b\" collides with one of \"a\""
+        );
+    }
+
+    #[test]
+    fn borrowed_keys_route_as_their_owned_forms() {
+        let ring: HashRing<_> = HashRing::builder()
+            .replicas(8)
+            .nodes(["a", "b", "c"])
+            .build();
+        let line = String::from("GET user:42 HTTP/1.1");
+        let key: &str = &line[4..11];
+
+        assert_eq!(ring.get_node(key), ring.get_node(&String::from("user:42")));
+        assert_eq!(ring.get_node("user:42"), ring.get_node(key));
+        let bytes: &[u8] = &[1, 2, 3];
+        assert_eq!(ring.get_node(bytes), ring.get_node(&vec![1u8, 2, 3]));
+    }
+
+    #[test]
+    fn partition_keys_puts_each_key_in_its_owners_group_once() {
+        let ring: HashRing<_> = HashRing::builder()
+            .replicas(8)
+            .nodes(["a", "b", "c"])
+            .build();
+        let keys = sample_keys(101, 300);
+
+        let partitions = ring.partition_keys(&keys);
+        let mut seen = 0;
+        for (node, group) in &partitions {
+            assert!(!group.is_empty());
+            for key in group {
+                assert_eq!(ring.get_node(key), Some(node));
+            }
+            seen += group.len();
+        }
+        assert_eq!(seen, keys.len());
+        let mut union: Vec<u64> = partitions.values().flatten().map(|&&key| key).collect();
+        let mut expected = keys.clone();
+        union.sort_unstable();
+        expected.sort_unstable();
+        assert_eq!(union, expected);
+    }
+
+    #[test]
+    fn double_hashing_spreads_points_a_weak_hasher_clusters() {
+        // The identity hasher puts replica `r` of node `n` at `n + r`, the
+        // worst case of a hasher that leaves near-identical inputs close.
+        let nodes = sample_keys(102, 8);
+        let build = |double_hashing| -> HashRing<u64, Identity> {
+            HashRing::builder()
+                .hasher(Identity::default())
+                .replicas(64)
+                .double_hashing(double_hashing)
+                .nodes(nodes.iter().copied())
+                .build()
+        };
+        let arc_ratio = |ring: &HashRing<u64, Identity>| {
+            let lengths = ring
+                .arcs()
+                .into_iter()
+                .map(|(_, start, end)| end.wrapping_sub(start).wrapping_add(1));
+            let (min, max) = lengths.fold((u64::MAX, 0), |(min, max), len| {
+                (min.min(len), max.max(len))
+            });
+            max as f64 / min as f64
+        };
+
+        let (single, double) = (build(false), build(true));
+        assert_eq!(single.iter().len(), double.iter().len());
+        assert!(arc_ratio(&single) > 1e15);
+        assert!(arc_ratio(&double) < 1e5);
+        assert!(double.imbalance_factor() < 2.0);
+        assert!(single.imbalance_factor() > 2.0 * double.imbalance_factor());
+    }
+
+    #[test]
+    fn a_double_hashed_ring_is_rebuilt_through_the_builder() {
+        let ring: HashRing<_> = HashRing::builder()
+            .replicas(8)
+            .double_hashing(true)
+            .nodes(["a", "b"])
+            .build();
+
+        assert_ne!(
+            HashRing::from_topology(ring.topology(), ring.replicas()),
+            ring
+        );
+        let rebuilt: HashRing<_> = HashRing::builder()
+            .replicas(ring.replicas())
+            .double_hashing(true)
+            .nodes(ring.topology().into_iter().map(|(node, _)| node))
+            .build();
+        assert_eq!(rebuilt, ring);
+    }
+
+    #[test]
+    fn get_node_wrapped_reports_lookups_past_the_top() {
+        let mut ring = HashRing::with_hasher(Identity::default());
+        ring.add_nodes([100u64, 200, 300]);
+
+        assert_eq!(ring.get_node_wrapped(&150u64), Some((&200, false)));
+        assert_eq!(ring.get_node_wrapped(&300u64), Some((&300, false)));
+        assert_eq!(ring.get_node_wrapped(&301u64), Some((&100, true)));
+        assert_eq!(ring.get_node_wrapped(&50u64), Some((&100, false)));
+
+        ring.set_health(|&node| node != 300);
+        assert_eq!(ring.get_node_wrapped(&250u64), Some((&100, true)));
+        assert_eq!(HashRing::<u64>::new().get_node_wrapped(&1u64), None);
+    }
+
+    #[test]
+    fn apply_changes_adds_then_removes() {
+        let mut ring = HashRing::with_replicas(8);
+        ring.add_nodes(["a", "b", "c"]);
+        let mut sequential = ring.clone();
+
+        ring.apply_changes(vec!["d", "e"], vec![&"a", &"e"]);
+        sequential.add_nodes(["d", "e"]);
+        sequential.remove_nodes(&["a", "e"]);
+        assert_eq!(ring, sequential);
+        assert!(ring.nodes().eq(&["b", "c", "d"]));
+    }
+
+    #[test]
+    fn largest_gap_finds_the_widest_arc_wrapping_or_not() {
+        let mut ring = HashRing::with_hasher(Identity::default());
+        ring.add_nodes([100u64, 200, 1_000]);
+        assert_eq!(ring.largest_gap(), Some((1_000, 100)));
+
+        ring.add_nodes([1 << 62, 3 << 62, u64::MAX - 50]);
+        assert_eq!(ring.largest_gap(), Some((1 << 62, 3 << 62)));
+        ring.remove_node(&(1 << 62));
+        assert_eq!(ring.largest_gap(), Some((1_000, 3 << 62)));
+
+        ring.clear();
+        assert_eq!(ring.largest_gap(), None);
+        ring.add_node(5);
+        assert_eq!(ring.largest_gap(), Some((5, 5)));
+    }
+
+    #[test]
+    fn set_weight_only_adds_or_removes_the_difference() {
+        let mut ring = HashRing::with_replicas(8);
+        ring.add_nodes(["a", "b"]);
+        let points = |ring: &HashRing<&'static str>| -> BTreeSet<(u64, &'static str)> {
+            ring.iter().map(|(&at, &node)| (at, node)).collect()
+        };
+        let before = points(&ring);
+
+        ring.set_weight(&"a", 3);
+        let raised = points(&ring);
+        assert!(before.is_subset(&raised));
+        assert_eq!(raised.len(), 32);
+        assert!(raised.difference(&before).all(|&(_, node)| node == "a"));
+        let mut placed = HashRing::with_replicas(8);
+        placed.add_node("b");
+        placed.add_weighted_node("a", 3);
+        assert_eq!(ring, placed);
+
+        ring.set_weight(&"a", 1);
+        assert_eq!(points(&ring), before);
+        ring.set_weight(&"z", 5);
+        assert_eq!(points(&ring), before);
+    }
+
+    #[test]
+    fn get_node_for_hash_is_get_node_without_the_hashing() {
+        let ring: HashRing<_> = HashRing::builder()
+            .replicas(8)
+            .salt(108)
+            .nodes(["a", "b", "c"])
+            .build();
+        for key in sample_keys(108, 500) {
+            assert_eq!(
+                ring.get_node_for_hash(ring.hash_one(&key)),
+                ring.get_node(&key)
+            );
+        }
+        assert_eq!(HashRing::<&str>::new().get_node_for_hash(0), None);
+    }
+
+    #[test]
+    fn every_key_in_a_group_lands_on_the_groups_node() {
+        let ring: HashRing<_> = HashRing::builder()
+            .replicas(8)
+            .nodes(["a", "b", "c", "d"])
+            .build();
+        let owner = ring.get_node_in_group("user:42");
+        assert!(owner.is_some());
+        assert_eq!(ring.get_node("user:42"), owner);
+
+        // The same rows spread out when each is hashed with its group.
+        let rows = sample_keys(109, 100);
+        let spread: BTreeSet<_> = rows
+            .iter()
+            .map(|row| ring.get_node_composite(&"user:42", row))
+            .collect();
+        assert!(spread.len() > 1);
+
+        let groups: BTreeSet<_> = (0..50).map(|user| ring.get_node_in_group(&user)).collect();
+        assert!(groups.len() > 1);
+    }
+
+    #[test]
+    fn assignment_digest_survives_reinsertion_and_changes_with_membership() {
+        let nodes = ["a", "b", "c", "d", "e"];
+        let keys = sample_keys(112, 500);
+        let mut ring = HashRing::with_replicas(16);
+        ring.add_nodes(nodes);
+        let before = ring.assignment_digest(&keys);
+
+        let mut rebuilt = HashRing::with_replicas(16);
+        rebuilt.add_nodes(shuffled(&nodes, 112));
+        assert_eq!(rebuilt.assignment_digest(&keys), before);
+
+        ring.remove_node(&"c");
+        ring.add_node("c");
+        assert_eq!(ring.assignment_digest(&keys), before);
+
+        ring.add_node("f");
+        assert_ne!(ring.assignment_digest(&keys), before);
+    }
+
+    #[test]
+    fn get_node_under_load_falls_through_to_the_next_node_under_cap() {
+        let mut ring = HashRing::with_hasher(Identity::default());
+        ring.add_nodes([100u64, 200, 300]);
+        let mut loads = BTreeMap::new();
+        assert_eq!(ring.get_node_under_load(&150u64, &loads, 5), Some(&200));
+
+        loads.insert(200, 5);
+        assert_eq!(ring.get_node_under_load(&150u64, &loads, 5), Some(&300));
+        loads.insert(300, 9);
+        assert_eq!(ring.get_node_under_load(&150u64, &loads, 5), Some(&100));
+        assert_eq!(ring.get_node_under_load(&150u64, &loads, 6), Some(&200));
+        loads.insert(100, 5);
+        assert_eq!(ring.get_node_under_load(&150u64, &loads, 5), None);
+
+        // The ring's own loads play no part.
+        assert_eq!(ring.get_node_bounded(&150u64, 1.0), Some(&200));
+        assert_eq!(
+            ring.get_node_under_load(&150u64, &BTreeMap::new(), 1),
+            Some(&200)
+        );
+    }
+
+    #[test]
+    fn coalesce_drops_runs_of_one_nodes_points_without_moving_keys() {
+        // Replica `r` of node `n` sits at `n + r`, so each node's four points
+        // are neighbours and only its highest one bounds its arc.
+        let mut ring = HashRing::with_replicas_and_hasher(4, Identity::default());
+        ring.add_nodes([100u64, 200, 300]);
+        let keys: Vec<u64> = sample_keys(114, 2_000)
+            .into_iter()
+            .chain((0..400).chain([u64::MAX]))
+            .collect();
+        let before: Vec<_> = keys.iter().map(|key| ring.get_node(key).copied()).collect();
+
+        assert_eq!(ring.iter().len(), 12);
+        assert_eq!(ring.coalesce(), 9);
+        assert_eq!(
+            ring.as_sorted_slice(),
+            [(103, &100), (203, &200), (303, &300)]
+        );
+        let after: Vec<_> = keys.iter().map(|key| ring.get_node(key).copied()).collect();
+        assert_eq!(after, before);
+        assert!(ring.check_invariants().is_ok());
+        assert_eq!(ring.coalesce(), 0);
+    }
+
+    #[test]
+    fn as_sorted_slice_is_ascending_and_bisects_like_get_node() {
+        let mut ring = HashRing::with_replicas(16);
+        ring.add_nodes(sample_keys(117, 300));
+        let points = ring.as_sorted_slice();
+        assert_eq!(points.len(), ring.iter().len());
+        assert!(points.windows(2).all(|pair| pair[0].0 < pair[1].0));
+
+        for key in sample_keys(1170, 2_000) {
+            let hash = ring.hash_one(&key);
+            let index = points.partition_point(|&(position, _)| position < hash);
+            let (_, node) = points.get(index).unwrap_or(&points[0]);
+            assert_eq!(Some(*node), ring.get_node(&key));
+        }
+    }
+
+    #[test]
+    fn records_sharing_a_field_share_their_nodes() {
+        struct Record {
+            partition: &'static str,
+            row: u64,
+        }
+        let mut ring = HashRing::with_replicas(16);
+        ring.add_nodes(["a", "b", "c", "d", "e", "f"]);
+        let partition = |record: &Record| record.partition;
+
+        let first = Record {
+            partition: "orders",
+            row: 1,
+        };
+        let second = Record {
+            partition: "orders",
+            row: 2,
+        };
+        let nodes = ring.get_nodes_by(&first, partition, 3);
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(ring.get_nodes_by(&second, partition, 3), nodes);
+        assert_eq!(ring.get_nodes(&"orders", 3), nodes);
+        assert_ne!(
+            ring.get_nodes_by(&first, |record| record.row, 3),
+            ring.get_nodes_by(&second, |record| record.row, 3)
+        );
+    }
+
+    #[test]
+    fn a_primary_reporting_no_capacity_sends_keys_to_the_next_node() {
+        let mut ring = HashRing::with_replicas(16);
+        ring.add_nodes(["a", "b", "c", "d"]);
+        for key in sample_keys(120, 500) {
+            let walk = ring.get_nodes(&key, 2);
+            assert_eq!(ring.get_node_dynamic(&key, |_| 10), Some(walk[0]));
+            let drained = |node: &&str| if node == walk[0] { 0 } else { 10 };
+            assert_eq!(ring.get_node_dynamic(&key, drained), Some(walk[1]));
+            assert_eq!(ring.get_node_dynamic(&key, |_| 0), None);
+        }
+
+        let mut ring = HashRing::with_hasher(Identity::default());
+        ring.add_nodes([100u64, 200, 300]);
+        assert_eq!(ring.get_node_dynamic(&150u64, |_| 1), Some(&200));
+        // Reaching 150 back from 300 is three times the 50 from 200, so a
+        // quadrupled capacity wins the key.
+        let boosted = |node: &u64| if *node == 300 { 4 } else { 1 };
+        assert_eq!(ring.get_node_dynamic(&150u64, boosted), Some(&300));
+    }
+
+    #[test]
+    fn arcs_tile_the_whole_hash_space() {
+        /// Checks that each arc starts just after the one before ends, and
+        /// returns the positions they cover between them.
+        fn covered(arcs: &[(&u64, u64, u64)]) -> u128 {
+            let mut previous_end = arcs.last().unwrap().2;
+            arcs.iter()
+                .map(|&(_, start, end)| {
+                    assert_eq!(start, previous_end.wrapping_add(1));
+                    previous_end = end;
+                    u128::from(end.wrapping_sub(start)) + 1
+                })
+                .sum()
+        }
+
+        let mut ring = HashRing::with_replicas(16);
+        ring.add_nodes(sample_keys(122, 50));
+        let arcs = ring.arcs();
+        assert_eq!(arcs.len(), ring.iter().len());
+        assert_eq!(covered(&arcs), 1 << 64);
+        for (node, _, end) in &arcs {
+            assert_eq!(ring.owner_of_position(*end), Some(*node));
+        }
+
+        let mut ends = HashRing::with_hasher(Identity::default());
+        ends.add_nodes([0u64, 500, u64::MAX]);
+        assert_eq!(
+            ends.arcs(),
+            [(&0, 0, 0), (&500, 1, 500), (&u64::MAX, 501, u64::MAX)]
+        );
+        assert_eq!(covered(&ends.arcs()), 1 << 64);
+
+        ends.remove_nodes(&[0, u64::MAX]);
+        assert_eq!(ends.arcs(), [(&500, 501, 500)]);
+        assert!(HashRing::<u64>::new().arcs().is_empty());
+    }
+
+    #[test]
+    fn a_draining_node_takes_no_new_keys_until_it_is_removed() {
+        let mut ring = HashRing::with_replicas(16);
+        ring.add_nodes(["a", "b", "c", "d"]);
+        let mut without = ring.clone();
+        without.remove_node(&"b");
+        let before = ring.clone();
+
+        ring.drain_node(&"b");
+        assert!(ring.contains_node(&"b") && ring.is_draining(&"b"));
+        assert_eq!(ring.len(), 4);
+        assert_eq!(ring.iter().len(), 64);
+
+        let keys = sample_keys(123, 1_000);
+        assert!(keys.iter().any(|key| before.get_node(key) == Some(&"b")));
+        for key in &keys {
+            let owner = ring.get_node(key).copied();
+            assert_eq!(owner.as_ref(), without.get_node(key));
+            assert_eq!(ring.get_node_allow_draining(key), before.get_node(key));
+
+            let chain = ring.failover_chain(key);
+            assert_eq!(chain.first().copied(), owner.as_ref());
+            assert_eq!(chain.last(), Some(&&"b"));
+            assert_eq!(ring.get_nodes(key, 4), without.get_nodes(key, 4));
+            assert_eq!(ring.get_node_excluding(key, &[]), owner.as_ref());
+            assert_ne!(
+                ring.get_node_under_load(key, &BTreeMap::new(), 1),
+                Some(&"b")
+            );
+            assert_eq!(ring.get_node_mut(key).map(|node| *node), owner);
+            assert_ne!(ring.get_node_bounded(key, 1.25), Some(&"b"));
+            assert_ne!(ring.get_node_by_bytes(&key.to_le_bytes()), Some(&"b"));
+        }
+        assert_eq!(ring.load(&"b"), 0);
+        assert!(before.shard_table(256).contains(&&"b"));
+        assert!(!ring.shard_table(256).contains(&&"b"));
+
+        // Removing the node finishes the drain without moving anything.
+        ring.remove_node(&"b");
+        assert!(!ring.contains_node(&"b") && !ring.is_draining(&"b"));
+        for key in &keys {
+            assert_eq!(ring.get_node_allow_draining(key), without.get_node(key));
+        }
+    }
+
+    /// The oracle of the `add_remove` fuzz target, driven by
+    /// [`sample_keys`] instead of the fuzzer: each step adds or removes one
+    /// of sixteen nodes, and afterwards the ring has to hold exactly the
+    /// nodes of a plain set and match a ring built from them afresh.
+    #[test]
+    fn add_and_remove_keep_the_ring_in_step_with_a_model_set() {
+        for seed in 0..4 {
+            let mut ring = HashRing::with_replicas(8);
+            let mut model = BTreeSet::new();
+            for step in sample_keys(125 + seed, 500) {
+                let node = (step & 0x0f) as u8;
+                if step & 0x80 == 0 {
+                    assert_eq!(ring.add_node(node), model.insert(node));
+                } else {
+                    assert_eq!(ring.remove_node(&node), model.take(&node));
+                }
+
+                assert!(ring.nodes().eq(model.iter()), "membership diverged");
+                assert_eq!(ring.len(), model.len());
+                assert_eq!(ring.check_invariants(), Ok(()));
+
+                let mut rebuilt = HashRing::with_replicas(8);
+                rebuilt.add_nodes(model.iter().copied());
+                assert_eq!(ring, rebuilt, "layout depends on history");
+                for key in 0..64u32 {
+                    let owner = ring.get_node(&key);
+                    assert_eq!(owner, rebuilt.get_node(&key));
+                    assert_eq!(owner.is_some(), !model.is_empty());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn get_node_stable_wraps_to_the_lowest_node_not_the_lowest_point() {
+        let mut ring = HashRing::with_hasher(Identity::default());
+        ring.add_node_at("b", 10);
+        ring.add_node_at("c", 300);
+        ring.add_node_at("a", 500);
+
+        // Below the top point both lookups agree.
+        for (key, owner) in [(0u64, "b"), (10, "b"), (11, "c"), (400, "a"), (500, "a")] {
+            assert_eq!(ring.get_node_stable(&key), Some(&owner));
+            assert_eq!(ring.get_node(&key), Some(&owner));
+        }
+        // Above it, `get_node` wraps to the point at 10.
+        for key in [501u64, 1 << 40, u64::MAX] {
+            assert_eq!(ring.get_node(&key), Some(&"b"));
+            assert_eq!(ring.get_node_stable(&key), Some(&"a"));
+        }
+
+        ring.drain_node(&"a");
+        assert_eq!(ring.get_node_stable(&u64::MAX), Some(&"a"));
+        ring.add_node_at("0", 50);
+        assert_eq!(ring.get_node_stable(&u64::MAX), Some(&"0"));
+        assert_eq!(ring.get_node_stable(&400u64), Some(&"a"));
+        ring.clear();
+        assert_eq!(ring.get_node_stable(&u64::MAX), None);
+    }
+
+    #[test]
+    fn with_capacity_builds_a_large_ring_with_every_point_reserved() {
+        let mut ring = HashRing::with_capacity(2_000, 40);
+        ring.add_nodes(0..2_000u64);
+        ring.add_weighted_node(2_000, 3);
+        assert_eq!(ring.len(), 2_001);
+        assert_eq!(ring.iter().len(), 2_000 * 40 + 3 * 40);
+        assert!(ring.check_invariants().is_ok());
+
+        for (node, entry) in &ring.nodes {
+            let points = if *node == 2_000 { 120 } else { 40 };
+            assert_eq!(entry.positions.len(), points);
+            assert_eq!(entry.positions.capacity(), points);
+        }
+    }
+}
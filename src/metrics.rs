@@ -0,0 +1,217 @@
+use alloc::{collections::BTreeMap, string::String};
+use core::{
+    fmt::{self, Display, Write},
+    hash::Hash,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::{HashRing, RingHasher};
+
+/// Lookup counts gathered by a [`HashRing`](crate::HashRing), returned by
+/// [`HashRing::stats`](crate::HashRing::stats).
+///
+/// Only available with the `metrics` feature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RingStats<T> {
+    /// The number of key lookups since the ring was created or last
+    /// cleared, including any made while it was empty.
+    pub lookups: u64,
+    /// The number of those lookups each node on the ring answered. A node's
+    /// count goes with it when it is removed or added again, so these only
+    /// add up to `lookups` while membership has only grown.
+    pub hits: BTreeMap<T, u64>,
+}
+
+/// A count that can be bumped through a shared reference, so lookups stay
+/// `&self` with the `metrics` feature on.
+#[derive(Debug, Default)]
+pub(crate) struct Counter(AtomicU64);
+
+impl Counter {
+    pub(crate) fn increment(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl Clone for Counter {
+    fn clone(&self) -> Self {
+        Counter(AtomicU64::new(self.get()))
+    }
+}
+
+impl<T: Hash + Ord + Clone + Display, S: RingHasher> HashRing<T, S> {
+    /// Renders the ring's lookup counts and each node's share of the hash
+    /// space in the Prometheus text exposition format, ready to be served
+    /// from a scrape endpoint.
+    ///
+    /// There are three metric families: `hash_ring_keyspace_share`, a gauge
+    /// per node of its [`load_distribution`](Self::load_distribution)
+    /// share; `hash_ring_lookups_total`, the ring's lookup count; and
+    /// `hash_ring_node_hits_total`, the lookups each node answered. Nodes
+    /// are labelled `node` with their `Display` output.
+    ///
+    /// Only available with the `metrics` feature.
+    pub fn prometheus_text(&self) -> String {
+        let shares = self.load_distribution();
+        let stats = self.stats();
+        let mut out = String::new();
+
+        out.push_str("# HELP hash_ring_keyspace_share Share of the hash space a node owns.\n");
+        out.push_str("# TYPE hash_ring_keyspace_share gauge\n");
+        for node in self.nodes.keys() {
+            let share = shares.get(node).copied().unwrap_or(0.0);
+            let _ = writeln!(
+                out,
+                "hash_ring_keyspace_share{{node=\"{}\"}} {share}",
+                LabelValue(node)
+            );
+        }
+
+        out.push_str("# HELP hash_ring_lookups_total Key lookups the ring has answered.\n");
+        out.push_str("# TYPE hash_ring_lookups_total counter\n");
+        let _ = writeln!(out, "hash_ring_lookups_total {}", stats.lookups);
+
+        out.push_str("# HELP hash_ring_node_hits_total Key lookups a node has answered.\n");
+        out.push_str("# TYPE hash_ring_node_hits_total counter\n");
+        for (node, hits) in &stats.hits {
+            let _ = writeln!(
+                out,
+                "hash_ring_node_hits_total{{node=\"{}\"}} {hits}",
+                LabelValue(node)
+            );
+        }
+        out
+    }
+}
+
+/// Writes a node's `Display` output with the escapes a Prometheus label value
+/// needs.
+struct LabelValue<'a, T>(&'a T);
+
+impl<T: Display> Display for LabelValue<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        struct Escaper<'a, 'b>(&'a mut fmt::Formatter<'b>);
+
+        impl Write for Escaper<'_, '_> {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                for c in s.chars() {
+                    match c {
+                        '\\' => self.0.write_str("\\\\")?,
+                        '"' => self.0.write_str("\\\"")?,
+                        '\n' => self.0.write_str("\\n")?,
+                        c => self.0.write_char(c)?,
+                    }
+                }
+                Ok(())
+            }
+        }
+
+        write!(Escaper(f), "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sample_keys;
+
+    #[test]
+    fn stats_count_every_lookup_once() {
+        let mut ring = HashRing::with_replicas(16);
+        ring.add_nodes(["a", "b", "c"]);
+        let keys = sample_keys(56, 300);
+        for key in &keys {
+            ring.get_node(key);
+        }
+
+        let stats = ring.stats();
+        assert_eq!(stats.lookups, 300);
+        assert_eq!(stats.hits.values().sum::<u64>(), 300);
+        assert!(stats.hits.values().all(|&hits| hits > 0));
+
+        ring.clear();
+        assert_eq!(
+            ring.stats(),
+            RingStats {
+                lookups: 0,
+                hits: BTreeMap::new()
+            }
+        );
+    }
+
+    /// Splits a sample line of the text exposition format into its metric
+    /// name, its `node` label if it has one, unescaped, and its value,
+    /// or returns `None` if the line is malformed.
+    fn parse_sample(line: &str) -> Option<(&str, Option<String>, f64)> {
+        let (series, value) = line.rsplit_once(' ')?;
+        let value = value.parse().ok()?;
+        let Some((name, labels)) = series.split_once('{') else {
+            return Some((series, None, value));
+        };
+        let quoted = labels.strip_prefix("node=\"")?.strip_suffix("\"}")?;
+        let mut node = String::new();
+        let mut chars = quoted.chars();
+        while let Some(c) = chars.next() {
+            node.push(match (c, c == '\\') {
+                (_, true) => match chars.next()? {
+                    'n' => '\n',
+                    escaped @ ('\\' | '"') => escaped,
+                    _ => return None,
+                },
+                ('"', false) => return None,
+                (c, false) => c,
+            });
+        }
+        Some((name, Some(node), value))
+    }
+
+    #[test]
+    fn prometheus_text_is_well_formed_with_a_series_per_node() {
+        let mut ring = HashRing::with_replicas(16);
+        ring.add_nodes(["a", "b", "quote\"back\\slash\nnewline"]);
+        for key in sample_keys(100, 50) {
+            ring.get_node(&key);
+        }
+
+        let text = ring.prometheus_text();
+        let mut families = BTreeMap::new();
+        let mut shares = 0.0;
+        for line in text.lines() {
+            if let Some(comment) = line.strip_prefix("# ") {
+                let mut words = comment.splitn(3, ' ');
+                let (kind, name) = (words.next().unwrap(), words.next().unwrap());
+                assert!(kind == "HELP" || kind == "TYPE", "{line}");
+                if kind == "TYPE" {
+                    assert!(matches!(words.next(), Some("gauge" | "counter")), "{line}");
+                    families.insert(name, 0);
+                }
+                continue;
+            }
+            let (name, node, value) = parse_sample(line).unwrap_or_else(|| panic!("{line}"));
+            *families
+                .get_mut(name)
+                .expect("a sample before its TYPE line") += 1;
+            if name == "hash_ring_keyspace_share" {
+                assert!(ring.nodes().any(|&known| Some(known) == node.as_deref()));
+                shares += value;
+            }
+            if name == "hash_ring_lookups_total" {
+                assert_eq!(value, 50.0);
+            }
+        }
+
+        assert!((shares - 1.0).abs() < 1e-9);
+        assert_eq!(
+            families,
+            BTreeMap::from([
+                ("hash_ring_keyspace_share", 3),
+                ("hash_ring_lookups_total", 1),
+                ("hash_ring_node_hits_total", 3),
+            ])
+        );
+    }
+}
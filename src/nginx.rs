@@ -0,0 +1,200 @@
+use alloc::{collections::BTreeMap, string::ToString};
+use core::fmt::Display;
+
+use crate::crc32::crc32;
+
+/// A ring laid out exactly the way nginx's `hash $key consistent;` lays out
+/// an upstream, so a Rust proxy can send each request key to the same
+/// upstream server an nginx instance would.
+///
+/// Nodes are named by their `Display` output, which should be the address
+/// as written in the upstream's `server` line, such as `"10.0.0.1:8080"` or
+/// `"unix:/tmp/app.sock"`. nginx splits that into a host and a port, and
+/// gives a server of weight `w` `160 * w` points: the CRC-32 of the host, a
+/// zero byte, the port and the previous point's hash as four little-endian
+/// bytes, starting from zero. Keys hash to the CRC-32 of their bytes and
+/// belong to the first point at or after that hash, wrapping around.
+///
+/// Where two points land on the same position the smallest node by `Ord`
+/// keeps it; nginx leaves that to the order its sort happens to produce.
+/// Failover to the next point when a server is down is left to the caller.
+#[derive(Debug, Clone)]
+pub struct NginxRing<T> {
+    nodes: BTreeMap<T, u32>,
+    continuum: BTreeMap<u32, T>,
+}
+
+impl<T> Default for NginxRing<T> {
+    fn default() -> Self {
+        NginxRing {
+            nodes: BTreeMap::new(),
+            continuum: BTreeMap::new(),
+        }
+    }
+}
+
+impl<T: Display + Ord + Clone> FromIterator<T> for NginxRing<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut ring = NginxRing::default();
+        for node in iter {
+            ring.add_node(node);
+        }
+        ring
+    }
+}
+
+/// Hashes `key` the way nginx does for consistent upstream lookups: the
+/// CRC-32 of its bytes.
+pub fn nginx_hash(key: &[u8]) -> u32 {
+    crc32(&[key])
+}
+
+impl<T: Display + Ord + Clone> NginxRing<T> {
+    /// Creates an empty ring.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of nodes.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns `true` if there are no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Adds `node` with a weight of one, nginx's default.
+    pub fn add_node(&mut self, node: T) {
+        self.add_weighted_node(node, 1);
+    }
+
+    /// Adds `node` with `weight`, as given by `weight=` on its `server`
+    /// line.
+    ///
+    /// Adding a node that is already on the ring replaces its weight.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `weight` is zero.
+    pub fn add_weighted_node(&mut self, node: T, weight: u32) {
+        assert!(weight > 0, "a node needs a weight of at least one");
+        self.nodes.insert(node, weight);
+        self.rebuild();
+    }
+
+    /// Removes `node`.
+    pub fn remove_node(&mut self, node: &T) {
+        if self.nodes.remove(node).is_some() {
+            self.rebuild();
+        }
+    }
+
+    /// Returns `true` if `node` has been added.
+    pub fn contains_node(&self, node: &T) -> bool {
+        self.nodes.contains_key(node)
+    }
+
+    /// Returns the node that owns `key`, or `None` if there are no nodes.
+    pub fn get_node<K: AsRef<[u8]> + ?Sized>(&self, key: &K) -> Option<&T> {
+        let hash = nginx_hash(key.as_ref());
+        self.continuum
+            .range(hash..)
+            .next()
+            .or_else(|| self.continuum.first_key_value())
+            .map(|(_, node)| node)
+    }
+
+    /// Returns an iterator over every point as `(position, node)`, in
+    /// ascending order of position.
+    pub fn points(&self) -> impl Iterator<Item = (u32, &T)> {
+        self.continuum
+            .iter()
+            .map(|(&position, node)| (position, node))
+    }
+
+    /// Returns an iterator over the nodes, in ascending order.
+    pub fn nodes(&self) -> impl Iterator<Item = &T> {
+        self.nodes.keys()
+    }
+
+    /// Lays the points out again. A node's points never depend on the other
+    /// nodes, but removing one has to hand its positions back to any node
+    /// it collided with, and starting over is the simple way to get that
+    /// right.
+    fn rebuild(&mut self) {
+        self.continuum.clear();
+
+        // Nodes are visited in ascending order, so the first to claim a
+        // position is the smallest one to collide there.
+        for (node, &weight) in &self.nodes {
+            let server = node.to_string();
+            let (host, port) = split_server(&server);
+            let mut previous = 0u32;
+            for _ in 0..u64::from(weight) * 160 {
+                let hash = crc32(&[host, &[0], port, &previous.to_le_bytes()]);
+                self.continuum.entry(hash).or_insert_with(|| node.clone());
+                previous = hash;
+            }
+        }
+    }
+}
+
+/// Splits a `server` address into the host and port nginx hashes: a
+/// `unix:` socket is all host, and otherwise the port is the run of digits
+/// after the last colon, if the address ends in one.
+fn split_server(server: &str) -> (&[u8], &[u8]) {
+    let bytes = server.as_bytes();
+    if bytes.len() >= 5 && bytes[..5].eq_ignore_ascii_case(b"unix:") {
+        return (&bytes[5..], &[]);
+    }
+
+    let digits = bytes
+        .iter()
+        .rev()
+        .take_while(|byte| byte.is_ascii_digit())
+        .count();
+    match bytes.len().checked_sub(digits + 1) {
+        Some(colon) if bytes[colon] == b':' => (&bytes[..colon], &bytes[colon + 1..]),
+        _ => (bytes, &[]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn points_and_lookups_match_nginx() {
+        let ring: NginxRing<&str> = ["10.0.0.1:8080", "10.0.0.2:8080"].into_iter().collect();
+
+        assert_eq!(ring.points().count(), 320);
+        for (node, first_points) in [
+            ("10.0.0.1:8080", [0xf1ea5a8f, 0x4bd08e68, 0x84e5eeee]),
+            ("10.0.0.2:8080", [0x1adde18c, 0xdc8610b0, 0xf935ad54]),
+        ] {
+            for position in first_points {
+                assert!(ring.points().any(|point| point == (position, &node)));
+            }
+        }
+
+        assert_eq!(nginx_hash(b"/index.html"), 0xea224b42);
+        assert_eq!(ring.get_node("/index.html"), Some(&"10.0.0.1:8080"));
+        assert_eq!(ring.get_node("/api/users/42"), Some(&"10.0.0.2:8080"));
+        assert_eq!(ring.get_node("foo"), Some(&"10.0.0.1:8080"));
+    }
+
+    #[test]
+    fn split_server_separates_host_and_port() {
+        assert_eq!(
+            split_server("10.0.0.1:8080"),
+            (&b"10.0.0.1"[..], &b"8080"[..])
+        );
+        assert_eq!(split_server("backend"), (&b"backend"[..], &b""[..]));
+        assert_eq!(
+            split_server("unix:/tmp/app.sock"),
+            (&b"/tmp/app.sock"[..], &b""[..])
+        );
+    }
+}
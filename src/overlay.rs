@@ -0,0 +1,122 @@
+use core::hash::Hash;
+
+use crate::{DefaultHashBuilder, HashRing, RingHasher};
+
+/// The ring a migration is moving away from and the one it is moving to,
+/// consulted together so keys can be served while their data moves.
+///
+/// The usual pattern is to read a key from its node on the old ring and
+/// write it to its nodes on both, until every key that moved has been
+/// copied over; then [`finish`](Self::finish) keeps the new ring alone.
+/// The two rings may hash differently, as when moving off `StdHashBuilder`
+/// to the default hasher.
+#[derive(Debug, Clone)]
+pub struct OverlayRing<T, S: RingHasher = DefaultHashBuilder, N: RingHasher = S> {
+    old: HashRing<T, S>,
+    new: HashRing<T, N>,
+}
+
+/// Where [`OverlayRing::route`] sends a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteDecision<T> {
+    /// Both rings give the key to the same node, so it needs no special
+    /// handling.
+    Same(T),
+    /// The key is moving from `old` to `new`: read it from `old`, and write
+    /// it to both.
+    Moved {
+        /// The key's node on the old ring.
+        old: T,
+        /// The key's node on the new ring.
+        new: T,
+    },
+}
+
+impl<T> RouteDecision<T> {
+    /// Returns the node to read the key from, which is its node on the old
+    /// ring.
+    pub fn read(&self) -> &T {
+        match self {
+            RouteDecision::Same(node) => node,
+            RouteDecision::Moved { old, .. } => old,
+        }
+    }
+
+    /// Returns `true` if the rings disagree about the key.
+    pub fn is_moved(&self) -> bool {
+        matches!(self, RouteDecision::Moved { .. })
+    }
+}
+
+impl<T, S: RingHasher, N: RingHasher> OverlayRing<T, S, N> {
+    /// Lays `new` over `old` for the length of a migration.
+    pub fn new(old: HashRing<T, S>, new: HashRing<T, N>) -> Self {
+        OverlayRing { old, new }
+    }
+
+    /// Returns the ring being migrated away from.
+    pub fn old_ring(&self) -> &HashRing<T, S> {
+        &self.old
+    }
+
+    /// Returns the ring being migrated to.
+    pub fn new_ring(&self) -> &HashRing<T, N> {
+        &self.new
+    }
+
+    /// Ends the migration, keeping only the new ring.
+    pub fn finish(self) -> HashRing<T, N> {
+        self.new
+    }
+}
+
+impl<T: Hash + Ord + Clone, S: RingHasher, N: RingHasher> OverlayRing<T, S, N> {
+    /// Looks `key` up on both rings and reports whether its node changed.
+    ///
+    /// Returns `None` if either ring is empty, since there is then no node
+    /// to read from or none to write to.
+    pub fn route<K: Hash + ?Sized>(&self, key: &K) -> Option<RouteDecision<&T>> {
+        let old = self.old.get_node(key)?;
+        let new = self.new.get_node(key)?;
+        Some(if old == new {
+            RouteDecision::Same(old)
+        } else {
+            RouteDecision::Moved { old, new }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sample_keys;
+
+    #[test]
+    fn only_keys_that_changed_hands_are_reported_moved() {
+        let old: HashRing<_> = HashRing::builder()
+            .replicas(16)
+            .nodes(["a", "b", "c"])
+            .build();
+        let mut new = old.clone();
+        new.add_node("d");
+        let overlay = OverlayRing::new(old.clone(), new.clone());
+
+        let mut moved = 0;
+        for key in sample_keys(88, 1_000) {
+            let (before, after) = (old.get_node(&key).unwrap(), new.get_node(&key).unwrap());
+            match overlay.route(&key).unwrap() {
+                RouteDecision::Same(node) => assert!(node == before && node == after),
+                RouteDecision::Moved { old, new } => {
+                    assert_eq!((old, new), (before, after));
+                    assert_eq!(new, &"d");
+                    moved += 1;
+                }
+            }
+        }
+        assert!(moved > 0);
+        assert_eq!(
+            OverlayRing::new(HashRing::<&str>::new(), new).route("key"),
+            None
+        );
+    }
+}
@@ -0,0 +1,314 @@
+use core::{
+    fmt::Debug,
+    hash::{BuildHasher, Hash},
+};
+
+use twox_hash::xxh3::{Hash128, HasherExt};
+
+/// A position on a [`HashRing`](crate::HashRing): an unsigned integer whose
+/// range, wrapping around at the top, is the ring.
+pub trait Position: Copy + Ord + Hash + Debug {
+    /// The position just after `0`.
+    const ONE: Self;
+
+    /// The number of bits in a position.
+    const BITS: u32;
+
+    /// The number of positions on the ring, as a float for share math.
+    const RING_SIZE: f64;
+
+    /// Adds `other` to `self`, wrapping around the ring.
+    fn wrapping_add(self, other: Self) -> Self;
+
+    /// Returns the clockwise distance from `other` to `self`.
+    fn wrapping_sub(self, other: Self) -> Self;
+
+    /// Converts the position to a float, rounding if it does not fit.
+    fn to_f64(self) -> f64;
+
+    /// Widens the position to a `u128`, keeping every bit.
+    fn to_u128(self) -> u128;
+
+    /// Converts the low [`BITS`](Self::BITS) bits of `bits` to a position,
+    /// discarding the rest.
+    fn truncate_from(bits: u128) -> Self;
+}
+
+macro_rules! impl_position {
+    ($($position:ty => $ring_size:expr),* $(,)?) => {$(
+        impl Position for $position {
+            const ONE: Self = 1;
+            const BITS: u32 = <$position>::BITS;
+            const RING_SIZE: f64 = $ring_size;
+
+            fn wrapping_add(self, other: Self) -> Self {
+                <$position>::wrapping_add(self, other)
+            }
+
+            fn wrapping_sub(self, other: Self) -> Self {
+                <$position>::wrapping_sub(self, other)
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+
+            fn to_u128(self) -> u128 {
+                self as u128
+            }
+
+            fn truncate_from(bits: u128) -> Self {
+                bits as $position
+            }
+        }
+    )*};
+}
+
+impl_position! {
+    u32 => 4_294_967_296.0,
+    u64 => 18_446_744_073_709_551_616.0,
+    u128 => 340_282_366_920_938_463_463_374_607_431_768_211_456.0,
+}
+
+/// Returns the length of the arc running clockwise from just after `prev`
+/// up to and including `next`, which is also how far clockwise `next` is
+/// from `prev`.
+///
+/// The subtraction wraps, so an arc across the top of the ring, from near
+/// the highest position to near zero, comes out right without overflowing.
+/// The one arc too long to represent is that of a point alone on the ring,
+/// when `prev` and `next` are the same point and the arc is the whole ring:
+/// it comes back as zero, which callers have to take as the full ring.
+pub(crate) fn arc_len<P: Position>(prev: P, next: P) -> P {
+    next.wrapping_sub(prev)
+}
+
+/// Hashes nodes and keys to positions on a [`HashRing`](crate::HashRing).
+///
+/// Every [`BuildHasher`] is a `RingHasher` with 64-bit positions, so any
+/// hasher that works with a `HashMap` works with a ring. Other position
+/// widths come from hashers that implement this trait directly, such as
+/// [`Xxh3Hash128Builder`] for 128 bits. A hasher with 32-bit output can do
+/// the same with `u32` positions, halving the size of every point, at the
+/// price of collisions turning up from tens of thousands of points on.
+pub trait RingHasher {
+    /// The type of the positions this hasher produces.
+    type Position: Position;
+
+    /// Hashes `value` to a position, starting from a fresh hasher so the
+    /// result depends on nothing hashed before.
+    fn hash_position<H: Hash + ?Sized>(&self, value: &H) -> Self::Position;
+}
+
+impl<S: BuildHasher> RingHasher for S {
+    type Position = u64;
+
+    fn hash_position<H: Hash + ?Sized>(&self, value: &H) -> u64 {
+        self.hash_one(value)
+    }
+}
+
+/// Hashes onto a 128-bit ring with XXH3's 128-bit variant.
+///
+/// With 64-bit positions, the chance that two of `n` points collide grows
+/// with `n^2 / 2^65`, which stops being negligible for rings with millions
+/// of points. At 128 bits it stays out of reach for any ring that fits in
+/// memory. XXH3 is a fixed algorithm, so for a given seed the positions are
+/// the same in every process and on every Rust release, as long as the
+/// `Hash` impls of the nodes and keys feed it the same bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Xxh3Hash128Builder {
+    seed: u64,
+}
+
+impl Xxh3Hash128Builder {
+    /// Creates a hasher that uses a zero seed, the same as
+    /// [`Default`].
+    pub fn new() -> Self {
+        Self::with_seed(0)
+    }
+
+    /// Creates a hasher that uses `seed`, for taking a ring's layout apart
+    /// from another's that holds the same nodes.
+    pub fn with_seed(seed: u64) -> Self {
+        Xxh3Hash128Builder { seed }
+    }
+}
+
+impl RingHasher for Xxh3Hash128Builder {
+    type Position = u128;
+
+    fn hash_position<H: Hash + ?Sized>(&self, value: &H) -> u128 {
+        let mut hasher = Hash128::with_seed(self.seed);
+        value.hash(&mut hasher);
+        hasher.finish_ext()
+    }
+}
+
+/// Returns the approximate chance that any two of `total_points` points
+/// land on the same position of a ring `bits` bits around.
+///
+/// This is the birthday approximation `1 - e^(-n(n - 1) / 2^(bits + 1))`,
+/// which is close whenever the ring has far more positions than there are
+/// points. It is for capacity planning: 2 million points on a 64-bit ring
+/// collide with a chance of about one in ten million, and when that is too
+/// high a 128-bit [`RingHasher`] such as [`Xxh3Hash128Builder`] makes it
+/// vanish. A collision costs little, since the ring keeps both nodes, but
+/// it does take a point's share of keys away from one of them.
+pub fn collision_probability(total_points: u64, bits: u32) -> f64 {
+    if total_points < 2 || bits > 1022 {
+        return 0.0;
+    }
+    let n = total_points as f64;
+    // 2^-(bits + 1), built from its exponent since `powi` needs `std`.
+    let scale = f64::from_bits(u64::from(1022 - bits) << 52);
+    let pairs = n * (n - 1.0) * scale;
+    one_minus_exp_neg(pairs)
+}
+
+/// Returns `1 - e^-x` for a non-negative `x`, without losing the digits
+/// that matter when `x` is tiny, and without the `std` float functions.
+fn one_minus_exp_neg(x: f64) -> f64 {
+    if x < 0.5 {
+        // 1 - e^-x = x - x^2/2! + x^3/3! - ..., whose terms shrink fast.
+        let mut term = x;
+        let mut sum: f64 = 0.0;
+        let mut k = 1.0;
+        while term.abs() > f64::EPSILON * sum.abs() {
+            sum += term;
+            k += 1.0;
+            term *= -x / k;
+        }
+        return sum;
+    }
+
+    // e^-x = 2^-k * e^-r with r in [0, ln 2), where the series for e^-r
+    // converges in a few dozen terms.
+    let k = (x / core::f64::consts::LN_2) as u64;
+    if k > 1022 {
+        return 1.0;
+    }
+    let r = x - k as f64 * core::f64::consts::LN_2;
+    let mut term: f64 = 1.0;
+    let mut exp = 0.0;
+    let mut n = 0.0;
+    while term.abs() > f64::EPSILON * exp {
+        exp += term;
+        n += 1.0;
+        term *= -r / n;
+    }
+    1.0 - exp * f64::from_bits((1023 - k) << 52)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::hash::Hasher;
+
+    use super::*;
+    use crate::HashRing;
+
+    /// FNV-1a with 32-bit output, for a ring with `u32` positions.
+    #[derive(Default)]
+    struct Fnv32Builder;
+
+    struct Fnv32(u32);
+
+    impl Hasher for Fnv32 {
+        fn finish(&self) -> u64 {
+            u64::from(self.0)
+        }
+
+        fn write(&mut self, bytes: &[u8]) {
+            for &byte in bytes {
+                self.0 = (self.0 ^ u32::from(byte)).wrapping_mul(0x0100_0193);
+            }
+        }
+    }
+
+    impl RingHasher for Fnv32Builder {
+        type Position = u32;
+
+        fn hash_position<H: Hash + ?Sized>(&self, value: &H) -> u32 {
+            let mut hasher = Fnv32(0x811c_9dc5);
+            value.hash(&mut hasher);
+            hasher.0
+        }
+    }
+
+    #[test]
+    fn a_128_bit_ring_routes_consistently() {
+        let mut ring = HashRing::with_replicas_and_hasher(16, Xxh3Hash128Builder::new());
+        ring.add_nodes(["a", "b", "c"]);
+        let mut again = HashRing::with_replicas_and_hasher(16, Xxh3Hash128Builder::new());
+        again.add_nodes(["c", "b", "a"]);
+
+        let hash: u128 = ring.hash_one("key");
+        assert!(hash > u128::from(u64::MAX) || ring.hash_one("other") > u128::from(u64::MAX));
+        for key in crate::sample_keys(44, 500) {
+            assert_eq!(ring.get_node(&key), again.get_node(&key));
+            let (node, hash) = ring.get_node_with_hash(&key).unwrap();
+            assert_eq!(ring.owner_of_position(hash), Some(node));
+        }
+    }
+
+    #[test]
+    fn a_32_bit_ring_routes_on_u32_positions() {
+        let mut ring = HashRing::with_replicas_and_hasher(16, Fnv32Builder);
+        ring.add_nodes(["a", "b", "c"]);
+        let points: Vec<(u32, &str)> = ring.iter().map(|(&at, &node)| (at, node)).collect();
+        assert_eq!(points.len(), 48);
+
+        for key in crate::sample_keys(52, 500) {
+            let hash: u32 = ring.hash_one(&key);
+            let owner = points
+                .iter()
+                .find(|&&(at, _)| at >= hash)
+                .unwrap_or(&points[0])
+                .1;
+            assert_eq!(ring.get_node(&key), Some(&owner));
+        }
+    }
+
+    #[test]
+    fn collision_probability_matches_the_birthday_bound() {
+        // 1 - e^-(n(n - 1) / 2^(bits + 1)), worked out by hand.
+        for (points, bits, expected) in [
+            (2, 1, 0.393_469_340_287_366_6),  // 1 - e^-0.5
+            (3, 2, 0.527_633_447_258_985_3),  // 1 - e^-0.75
+            (17, 8, 0.412_130_326_877_653_5), // 1 - e^-0.53125
+            (2_000_000, 64, 1.084_201_571_609_761_5e-7),
+        ] {
+            let probability = collision_probability(points, bits);
+            assert!(
+                (probability - expected).abs() <= expected * 1e-12,
+                "{points} points in {bits} bits: {probability}"
+            );
+        }
+        assert_eq!(collision_probability(0, 64), 0.0);
+        assert_eq!(collision_probability(1, 64), 0.0);
+        assert_eq!(collision_probability(u64::MAX, 16), 1.0);
+    }
+
+    #[test]
+    fn arc_len_wraps_across_the_top_of_the_ring() {
+        assert_eq!(arc_len(100u64, 300), 200);
+        assert_eq!(arc_len(u64::MAX, 0), 1);
+        assert_eq!(arc_len(u64::MAX - 5, 4), 10);
+        assert_eq!(arc_len(1u64, 0), u64::MAX);
+        assert_eq!(arc_len(0u64, u64::MAX), u64::MAX);
+        assert_eq!(arc_len(u32::MAX, 0u32), 1);
+        assert_eq!(arc_len(7u64, 7), 0);
+
+        // Points at both ends of the ring leave one arc of a single position
+        // across the top and one of all the rest.
+        let mut ring = HashRing::with_hasher(crate::tests::Identity::default());
+        ring.add_nodes([0u64, u64::MAX]);
+        let shares = ring.load_distribution();
+        assert!(shares[&0] < 1e-18);
+        assert!((shares[&u64::MAX] - 1.0).abs() < 1e-12);
+        assert_eq!(ring.get_node_range(&5u64), Some((&u64::MAX, 1..=u64::MAX)));
+        assert_eq!(ring.get_node_range(&0u64), Some((&0, 0..=0)));
+        assert_eq!(ring.largest_gap(), Some((0, u64::MAX)));
+    }
+}
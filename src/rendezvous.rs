@@ -0,0 +1,231 @@
+use alloc::collections::BTreeMap;
+use core::hash::{BuildHasher, Hash};
+
+use crate::DefaultHashBuilder;
+
+/// Routes keys with rendezvous, or highest random weight, hashing.
+///
+/// Every node is scored against the key with `hash((node, key))` and the
+/// highest score wins. There are no points on a ring and so no replicas to
+/// tune: assignments are balanced with one entry per node, and removing a
+/// node only remaps the keys it owned. The cost is a lookup that is linear
+/// in the number of nodes rather than logarithmic.
+///
+/// Nodes can be weighted. The hash is mapped to a number `u` between zero
+/// and one, and a node's score is `-weight / ln(u)`, which makes the chance
+/// of a node winning a key proportional to its weight.
+///
+/// The API mirrors [`HashRing`](crate::HashRing) so the two can be swapped.
+#[derive(Debug, Clone)]
+pub struct RendezvousRing<T, S = DefaultHashBuilder> {
+    nodes: BTreeMap<T, u32>,
+    hasher: S,
+}
+
+impl<T, S: Default> Default for RendezvousRing<T, S> {
+    fn default() -> Self {
+        RendezvousRing {
+            nodes: BTreeMap::new(),
+            hasher: S::default(),
+        }
+    }
+}
+
+impl<T: Hash + Ord, S: BuildHasher + Default> FromIterator<T> for RendezvousRing<T, S> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        RendezvousRing {
+            nodes: iter.into_iter().map(|node| (node, 1)).collect(),
+            hasher: S::default(),
+        }
+    }
+}
+
+impl<T: Hash + Ord, S: BuildHasher> RendezvousRing<T, S> {
+    /// Creates an empty ring that scores nodes with `hasher`.
+    pub fn with_hasher(hasher: S) -> Self {
+        RendezvousRing {
+            nodes: BTreeMap::new(),
+            hasher,
+        }
+    }
+
+    /// Returns the number of nodes.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns `true` if there are no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Adds `node` with a weight of one.
+    pub fn add_node(&mut self, node: T) {
+        self.add_weighted_node(node, 1);
+    }
+
+    /// Adds `node` with `weight`, so it wins about `weight` times as many
+    /// keys as a node of weight one.
+    ///
+    /// Adding a node that is already present replaces its weight.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `weight` is zero.
+    pub fn add_weighted_node(&mut self, node: T, weight: u32) {
+        assert!(weight > 0, "a node needs a weight of at least one");
+        self.nodes.insert(node, weight);
+    }
+
+    /// Removes `node`.
+    pub fn remove_node(&mut self, node: &T) {
+        self.nodes.remove(node);
+    }
+
+    /// Returns `true` if `node` has been added.
+    pub fn contains_node(&self, node: &T) -> bool {
+        self.nodes.contains_key(node)
+    }
+
+    /// Returns the node with the highest score for `key`, or `None` if there
+    /// are no nodes.
+    ///
+    /// Equal scores are rare but possible, and are settled so the same node
+    /// always wins: the one whose own hash is higher, and failing that the
+    /// smallest by `Ord`, the same rule [`HashRing`](crate::HashRing) uses
+    /// for colliding points.
+    pub fn get_node<K: Hash + ?Sized>(&self, key: &K) -> Option<&T> {
+        self.nodes
+            .iter()
+            .map(|(node, &weight)| (node, self.score(node, weight, key)))
+            .max_by(|(a, a_score), (b, b_score)| {
+                a_score
+                    .total_cmp(b_score)
+                    .then_with(|| self.hasher.hash_one(a).cmp(&self.hasher.hash_one(b)))
+                    .then_with(|| b.cmp(a))
+            })
+            .map(|(node, _)| node)
+    }
+
+    /// Returns an iterator over the nodes, in ascending order.
+    pub fn nodes(&self) -> impl Iterator<Item = &T> {
+        self.nodes.keys()
+    }
+
+    fn score<K: Hash + ?Sized>(&self, node: &T, weight: u32, key: &K) -> f64 {
+        // The top 53 bits of the hash, offset by half a step, give a float
+        // strictly between zero and one, so the logarithm is finite and
+        // negative.
+        let hash = self.hasher.hash_one((node, key));
+        let unit = ((hash >> 11) as f64 + 0.5) / (1u64 << 53) as f64;
+        -f64::from(weight) / ln(unit)
+    }
+}
+
+/// Returns the natural logarithm of a positive, finite, normal `x`.
+///
+/// `f64::ln` needs `std`. Splitting `x` into `m * 2^e` with `m` in `[1, 2)`
+/// leaves `ln(m)`, which the series `2 * atanh((m - 1) / (m + 1))` converges
+/// on quickly since its argument is at most a third.
+pub(crate) fn ln(x: f64) -> f64 {
+    let bits = x.to_bits();
+    let exponent = ((bits >> 52) & 0x7ff) as i64 - 1023;
+    let mantissa = f64::from_bits((bits & 0x000f_ffff_ffff_ffff) | 0x3ff0_0000_0000_0000);
+
+    let z = (mantissa - 1.0) / (mantissa + 1.0);
+    let z2 = z * z;
+    let mut term = z;
+    let mut sum: f64 = 0.0;
+    let mut n = 1.0;
+    while term.abs() > f64::EPSILON * sum.abs() {
+        sum += term / n;
+        term *= z2;
+        n += 2.0;
+    }
+    exponent as f64 * core::f64::consts::LN_2 + 2.0 * sum
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::hash::{BuildHasherDefault, Hasher};
+
+    use super::*;
+    use crate::sample_keys;
+
+    /// Hashes to whatever was written last, so a node and key pair hashes
+    /// the same for every node whenever the key is written last.
+    #[derive(Default)]
+    struct LastWriteHasher(u64);
+
+    impl Hasher for LastWriteHasher {
+        fn finish(&self) -> u64 {
+            self.0
+        }
+
+        fn write(&mut self, bytes: &[u8]) {
+            let mut word = [0; 8];
+            let len = bytes.len().min(8);
+            word[..len].copy_from_slice(&bytes[..len]);
+            self.0 = u64::from_le_bytes(word);
+        }
+    }
+
+    type LastWrite = BuildHasherDefault<LastWriteHasher>;
+
+    #[test]
+    fn removing_a_node_only_remaps_its_keys() {
+        let mut ring: RendezvousRing<&str> = ["a", "b", "c", "d"].into_iter().collect();
+        let keys = sample_keys(34, 5_000);
+        let before: Vec<&str> = keys
+            .iter()
+            .map(|key| *ring.get_node(key).unwrap())
+            .collect();
+
+        ring.remove_node(&"b");
+        assert!(!ring.contains_node(&"b"));
+        for (key, owner) in keys.iter().zip(before) {
+            let now = *ring.get_node(key).unwrap();
+            if owner == "b" {
+                assert_ne!(now, "b");
+            } else {
+                assert_eq!(now, owner);
+            }
+        }
+    }
+
+    #[test]
+    fn a_node_of_double_weight_wins_about_double_the_keys() {
+        let mut ring: RendezvousRing<&str> = RendezvousRing::default();
+        ring.add_node("a");
+        ring.add_weighted_node("b", 2);
+        ring.add_node("c");
+
+        let keys = sample_keys(70, 20_000);
+        let heavy = keys
+            .iter()
+            .filter(|key| ring.get_node(key) == Some(&"b"))
+            .count();
+        // An exact share would be half of the keys.
+        assert!((9_500..10_500).contains(&heavy), "{heavy}");
+    }
+
+    #[test]
+    fn tied_scores_go_to_the_same_node_every_time() {
+        // Every pair ends by writing the string key's terminator, so all
+        // nodes score alike and the node hash, then `Ord`, decides.
+        let mut by_hash = RendezvousRing::with_hasher(LastWrite::default());
+        for node in [3u64, 1, 2] {
+            by_hash.add_node(node);
+        }
+        let mut by_ord = RendezvousRing::with_hasher(LastWrite::default());
+        for node in ["b", "c", "a"] {
+            by_ord.add_node(node);
+        }
+
+        for key in ["x", "y", "z"] {
+            assert_eq!(by_hash.get_node(key), Some(&3));
+            assert_eq!(by_ord.get_node(key), Some(&"a"));
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use alloc::vec::Vec;
+
+/// Returns `count` well spread `u64` keys drawn from `seed`, the same ones
+/// for the same seed on every run and every platform.
+///
+/// This is for measuring a ring's distribution reproducibly, without a
+/// random number crate: a statistical check over these keys gives the same
+/// answer each time it runs. The keys come from SplitMix64, which is quick
+/// and spreads even consecutive seeds apart, but is no use where the keys
+/// must not be guessable. [`HashRing::placement_stability`] samples with
+/// seed `0`.
+///
+/// [`HashRing::placement_stability`]: crate::HashRing::placement_stability
+pub fn sample_keys(seed: u64, count: usize) -> Vec<u64> {
+    let mut state = seed;
+    (0..count)
+        .map(|_| {
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^ (z >> 31)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_same_seed_gives_the_same_keys() {
+        // SplitMix64's published outputs for a seed of zero.
+        assert_eq!(
+            sample_keys(0, 3),
+            [
+                0xe220_a839_7b1d_cdaf,
+                0x6e78_9e6a_a1b9_65f4,
+                0x06c4_5d18_8009_454f
+            ]
+        );
+        assert_eq!(sample_keys(107, 1_000), sample_keys(107, 1_000));
+        assert_eq!(sample_keys(107, 1_000)[..10], sample_keys(107, 10));
+        assert_ne!(sample_keys(107, 10), sample_keys(108, 10));
+        assert!(sample_keys(107, 0).is_empty());
+    }
+}
@@ -0,0 +1,157 @@
+use alloc::vec::Vec;
+use core::hash::Hash;
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{HashRing, RingHasher};
+
+/// A ring as serde sees it: its settings and every node with its weight and
+/// the positions of its points, as `P`.
+#[derive(Serialize, Deserialize)]
+struct RingRepr<N, P> {
+    replicas: usize,
+    salt: u64,
+    double_hashing: bool,
+    nodes: Vec<NodeRepr<N, P>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct NodeRepr<N, P> {
+    node: N,
+    weight: u32,
+    positions: P,
+}
+
+/// Serializes the ring's replica count, [`salt`](HashRing::salt), whether
+/// it uses [double hashing](crate::HashRingBuilder::double_hashing), and
+/// each node in ascending order with its weight and the positions of its
+/// points.
+///
+/// The points are written out rather than left to be hashed again, so
+/// those of a [`replica_key`](crate::HashRingBuilder::replica_key) or from
+/// [`add_node_at`](HashRing::add_node_at) survive too. The hasher, replica
+/// key, health check, draining nodes and bounded-load counts are not
+/// included. [`to_bytes`](HashRing::to_bytes) is far more compact.
+///
+/// Only available with the `serde` feature.
+impl<T, S> Serialize for HashRing<T, S>
+where
+    T: Serialize,
+    S: RingHasher,
+    S::Position: Serialize,
+{
+    fn serialize<Z: Serializer>(&self, serializer: Z) -> Result<Z::Ok, Z::Error> {
+        RingRepr {
+            replicas: self.replicas,
+            salt: self.salt,
+            double_hashing: self.double_hashing,
+            nodes: self
+                .nodes
+                .iter()
+                .map(|(node, entry)| NodeRepr {
+                    node,
+                    weight: entry.weight,
+                    positions: &entry.positions,
+                })
+                .collect(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Rebuilds a ring serialized as described on its `Serialize` impl,
+/// hashing with a default `S`, with every point where it was.
+///
+/// Keys are routed as on the ring serialized if that one hashed the same
+/// way as a default `S` does. Fails on a replica count or weight of zero, a
+/// node listed twice or a node with no points.
+///
+/// Only available with the `serde` feature.
+impl<'de, T, S> Deserialize<'de> for HashRing<T, S>
+where
+    T: Hash + Ord + Clone + Deserialize<'de>,
+    S: RingHasher + Default,
+    S::Position: Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = RingRepr::<T, Vec<S::Position>>::deserialize(deserializer)?;
+        if repr.replicas == 0 {
+            return Err(de::Error::custom("a hash ring needs at least one replica"));
+        }
+
+        let mut ring = Self::with_replicas_and_hasher(repr.replicas, S::default());
+        ring.salt = repr.salt;
+        ring.double_hashing = repr.double_hashing;
+        for NodeRepr {
+            node,
+            weight,
+            positions,
+        } in repr.nodes
+        {
+            if weight == 0 || positions.is_empty() {
+                return Err(de::Error::custom("a node needs a weight and points"));
+            }
+            if ring.contains_node(&node) {
+                return Err(de::Error::custom("a node is listed twice"));
+            }
+            ring.place_node(node, weight, positions);
+        }
+        Ok(ring)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::String, vec};
+
+    use super::*;
+    use crate::sample_keys;
+
+    #[test]
+    fn a_ring_routes_alike_after_a_json_round_trip() {
+        let mut ring: HashRing<String> = HashRing::builder()
+            .replicas(16)
+            .salt(11)
+            .double_hashing(true)
+            .build();
+        ring.add_nodes(["a", "b", "c"].map(String::from));
+        ring.add_weighted_node(String::from("d"), 3);
+        ring.add_node_at(String::from("e"), 42);
+
+        let json = serde_json::to_string(&ring).unwrap();
+        let restored: HashRing<String> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, ring);
+        assert_eq!(restored.salt(), 11);
+        assert_eq!(restored.topology(), ring.topology());
+        for key in sample_keys(11, 2_000) {
+            assert_eq!(restored.get_node(&key), ring.get_node(&key));
+        }
+
+        // The settings carry on to nodes added afterwards.
+        let (mut grown, mut restored_grown) = (ring, restored);
+        grown.add_node(String::from("f"));
+        restored_grown.add_node(String::from("f"));
+        assert_eq!(restored_grown, grown);
+    }
+
+    #[test]
+    fn inconsistent_rings_are_turned_away() {
+        for json in [
+            r#"{"replicas":0,"salt":0,"double_hashing":false,"nodes":[]}"#,
+            r#"{"replicas":1,"salt":0,"double_hashing":false,"nodes":[{"node":1,"weight":0,"positions":[5]}]}"#,
+            r#"{"replicas":1,"salt":0,"double_hashing":false,"nodes":[{"node":1,"weight":1,"positions":[]}]}"#,
+            r#"{"replicas":1,"salt":0,"double_hashing":false,"nodes":[{"node":1,"weight":1,"positions":[5]},{"node":1,"weight":1,"positions":[6]}]}"#,
+        ] {
+            assert!(
+                serde_json::from_str::<HashRing<u64>>(json).is_err(),
+                "{json}"
+            );
+        }
+
+        let ring: HashRing<u64> = serde_json::from_str(
+            r#"{"replicas":1,"salt":0,"double_hashing":false,"nodes":[{"node":1,"weight":1,"positions":[5,9]}]}"#,
+        )
+        .unwrap();
+        assert_eq!(ring.as_sorted_slice(), vec![(5, &1), (9, &1)]);
+    }
+}
@@ -0,0 +1,229 @@
+use alloc::borrow::Cow;
+use core::{
+    fmt::Display,
+    hash::{BuildHasher, Hash},
+};
+
+use crate::{HashRing, JumpHashRing, KetamaRing, NginxRing, RendezvousRing, RingHasher};
+
+/// A way of routing keys of type `K` to a changing set of nodes, for code
+/// that should work the same whichever one it is given.
+///
+/// [`HashRing`], [`RendezvousRing`], [`KetamaRing`] and [`NginxRing`] all
+/// implement it, the first two for any `Hash` key and the others for keys
+/// that are bytes, and it can be used as a `Box<dyn ConsistentHash<T>>`.
+/// [`JumpHashRing`] implements it too, with its `u32` bucket numbers as the
+/// nodes: adding one grows the ring by a bucket, and only the last bucket
+/// can be removed. Each type has far more to offer through its own methods.
+pub trait ConsistentHash<T: Clone, K: ?Sized = str> {
+    /// Adds `node`, with a weight of one where the type has weights.
+    fn add_node(&mut self, node: T);
+
+    /// Removes `node`, returning `false` and doing nothing if it is not
+    /// there or the type cannot remove it.
+    fn remove_node(&mut self, node: &T) -> bool;
+
+    /// Returns the node that owns `key`, or `None` if there are no nodes.
+    ///
+    /// The rings that keep their nodes lend one out; a [`JumpHashRing`]
+    /// computes its bucket and returns it by value.
+    fn get_node(&self, key: &K) -> Option<Cow<'_, T>>;
+}
+
+impl<T, K, S> ConsistentHash<T, K> for HashRing<T, S>
+where
+    T: Hash + Ord + Clone,
+    K: Hash + ?Sized,
+    S: RingHasher,
+{
+    fn add_node(&mut self, node: T) {
+        HashRing::add_node(self, node);
+    }
+
+    fn remove_node(&mut self, node: &T) -> bool {
+        HashRing::remove_node(self, node).is_some()
+    }
+
+    fn get_node(&self, key: &K) -> Option<Cow<'_, T>> {
+        HashRing::get_node(self, key).map(Cow::Borrowed)
+    }
+}
+
+impl<T, K, S> ConsistentHash<T, K> for RendezvousRing<T, S>
+where
+    T: Hash + Ord + Clone,
+    K: Hash + ?Sized,
+    S: BuildHasher,
+{
+    fn add_node(&mut self, node: T) {
+        RendezvousRing::add_node(self, node);
+    }
+
+    fn remove_node(&mut self, node: &T) -> bool {
+        let present = self.contains_node(node);
+        RendezvousRing::remove_node(self, node);
+        present
+    }
+
+    fn get_node(&self, key: &K) -> Option<Cow<'_, T>> {
+        RendezvousRing::get_node(self, key).map(Cow::Borrowed)
+    }
+}
+
+impl<T, K> ConsistentHash<T, K> for KetamaRing<T>
+where
+    T: Display + Ord + Clone,
+    K: AsRef<[u8]> + ?Sized,
+{
+    fn add_node(&mut self, node: T) {
+        KetamaRing::add_node(self, node);
+    }
+
+    fn remove_node(&mut self, node: &T) -> bool {
+        let present = self.contains_node(node);
+        KetamaRing::remove_node(self, node);
+        present
+    }
+
+    fn get_node(&self, key: &K) -> Option<Cow<'_, T>> {
+        KetamaRing::get_node(self, key).map(Cow::Borrowed)
+    }
+}
+
+impl<T, K> ConsistentHash<T, K> for NginxRing<T>
+where
+    T: Display + Ord + Clone,
+    K: AsRef<[u8]> + ?Sized,
+{
+    fn add_node(&mut self, node: T) {
+        NginxRing::add_node(self, node);
+    }
+
+    fn remove_node(&mut self, node: &T) -> bool {
+        let present = self.contains_node(node);
+        NginxRing::remove_node(self, node);
+        present
+    }
+
+    fn get_node(&self, key: &K) -> Option<Cow<'_, T>> {
+        NginxRing::get_node(self, key).map(Cow::Borrowed)
+    }
+}
+
+impl<K, S> ConsistentHash<u32, K> for JumpHashRing<S>
+where
+    K: Hash + ?Sized,
+    S: BuildHasher,
+{
+    /// Adds a bucket at the end of the range. Buckets are numbered from
+    /// zero up, so the new one is numbered [`len`](JumpHashRing::len) before
+    /// the call, whatever `bucket` is.
+    fn add_node(&mut self, bucket: u32) {
+        let _ = bucket;
+        self.add_bucket();
+    }
+
+    /// Removes `bucket` only if it is the last one.
+    fn remove_node(&mut self, bucket: &u32) -> bool {
+        if bucket.checked_add(1) != Some(self.len()) {
+            return false;
+        }
+        self.remove_bucket().is_some()
+    }
+
+    fn get_node(&self, key: &K) -> Option<Cow<'_, u32>> {
+        self.get_bucket(key).map(Cow::Owned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{boxed::Box, format, string::String, vec::Vec};
+
+    use super::*;
+
+    /// Checks what every strategy has to do: route each key to a node that
+    /// was added, the same way every time, and move only the keys of a node
+    /// that is removed.
+    fn routes_consistently(ring: &mut dyn ConsistentHash<String>) {
+        let keys: Vec<String> = (0..500).map(|key| format!("key-{key}")).collect();
+        assert_eq!(ring.get_node("key"), None);
+
+        for node in ["10.0.0.1:80", "10.0.0.2:80", "10.0.0.3:80", "10.0.0.4:80"] {
+            ring.add_node(String::from(node));
+        }
+        let before: Vec<String> = keys
+            .iter()
+            .map(|key| ring.get_node(key).unwrap().into_owned())
+            .collect();
+        for node in ["10.0.0.1:80", "10.0.0.2:80", "10.0.0.3:80", "10.0.0.4:80"] {
+            assert!(before.iter().any(|owner| owner == node));
+        }
+
+        let removed = String::from("10.0.0.2:80");
+        assert!(ring.remove_node(&removed));
+        assert!(!ring.remove_node(&String::from("10.0.0.9:80")));
+        for (key, owner) in keys.iter().zip(&before) {
+            let now = ring.get_node(key).unwrap();
+            if *owner == removed {
+                assert_ne!(*now, removed);
+            } else {
+                assert_eq!(*now, *owner);
+            }
+        }
+
+        ring.add_node(removed);
+        for (key, owner) in keys.iter().zip(&before) {
+            assert_eq!(ring.get_node(key).as_deref(), Some(owner));
+        }
+    }
+
+    #[test]
+    fn every_strategy_routes_consistently_through_the_trait() {
+        let strategies: [Box<dyn ConsistentHash<String>>; 4] = [
+            Box::new(HashRing::with_replicas(16)),
+            Box::new(RendezvousRing::<String>::default()),
+            Box::new(KetamaRing::new()),
+            Box::new(NginxRing::new()),
+        ];
+        for mut ring in strategies {
+            routes_consistently(ring.as_mut());
+        }
+    }
+
+    #[test]
+    fn a_jump_ring_grows_and_shrinks_at_the_end_through_the_trait() {
+        let mut ring: Box<dyn ConsistentHash<u32>> = Box::new(JumpHashRing::new(0));
+        assert_eq!(ring.get_node("key"), None);
+        for bucket in 0..4 {
+            ring.add_node(bucket);
+        }
+
+        let keys: Vec<String> = (0..500).map(|key| format!("key-{key}")).collect();
+        let before: Vec<u32> = keys
+            .iter()
+            .map(|key| ring.get_node(key).unwrap().into_owned())
+            .collect();
+        for bucket in 0..4 {
+            assert!(before.contains(&bucket));
+        }
+
+        // Only the last bucket can go, and only its keys move.
+        assert!(!ring.remove_node(&1));
+        assert!(!ring.remove_node(&4));
+        assert!(ring.remove_node(&3));
+        for (key, &owner) in keys.iter().zip(&before) {
+            let now = *ring.get_node(key).unwrap();
+            if owner == 3 {
+                assert!(now < 3);
+            } else {
+                assert_eq!(now, owner);
+            }
+        }
+
+        ring.add_node(3);
+        for (key, &owner) in keys.iter().zip(&before) {
+            assert_eq!(ring.get_node(key).as_deref(), Some(&owner));
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use alloc::collections::BTreeMap;
+use core::hash::Hash;
+
+use crate::{DefaultHashBuilder, HashRing, RingHasher};
+
+/// Routes keys in two steps: to a cluster `C` on an outer ring, and then to
+/// a node `N` on that cluster's own ring.
+///
+/// This is for hierarchies such as regions or clusters of machines, where
+/// membership changes within a cluster should stay within it. A key keeps
+/// its cluster while nodes come and go inside clusters, and keeps its node
+/// while other clusters change.
+///
+/// The inner step routes the key paired with its cluster rather than the
+/// key alone. The keys a cluster gets all hash into the arcs it owns on the
+/// outer ring, so routing them by the same hash again would hand them only
+/// to the few inner nodes owning those arcs and leave the rest idle.
+#[derive(Debug, Clone)]
+pub struct TieredRing<C, N, S: RingHasher = DefaultHashBuilder> {
+    clusters: HashRing<C, S>,
+    nodes: BTreeMap<C, HashRing<N, S>>,
+}
+
+impl<C, N, S: RingHasher + Default> Default for TieredRing<C, N, S> {
+    fn default() -> Self {
+        TieredRing {
+            clusters: HashRing::default(),
+            nodes: BTreeMap::new(),
+        }
+    }
+}
+
+impl<C: Hash + Ord + Clone, N: Hash + Ord + Clone> TieredRing<C, N> {
+    /// Creates a tiered ring with no clusters, whose outer ring has one
+    /// point per cluster and the default hasher.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<C, N, S> TieredRing<C, N, S>
+where
+    C: Hash + Ord + Clone,
+    N: Hash + Ord + Clone,
+    S: RingHasher,
+{
+    /// Creates a tiered ring that places clusters with `clusters`, which
+    /// fixes the outer ring's replicas, hasher and so on. Clusters already
+    /// on it have no nodes until [`add_cluster`](Self::add_cluster) gives
+    /// them some, and keys routed to them find none.
+    pub fn with_cluster_ring(clusters: HashRing<C, S>) -> Self {
+        TieredRing {
+            clusters,
+            nodes: BTreeMap::new(),
+        }
+    }
+
+    /// Adds `cluster` with `nodes` as its ring, replacing the ring of a
+    /// cluster that is already there, which keeps its place on the outer
+    /// ring.
+    pub fn add_cluster(&mut self, cluster: C, nodes: HashRing<N, S>) {
+        self.clusters.add_node(cluster.clone());
+        self.nodes.insert(cluster, nodes);
+    }
+
+    /// Removes `cluster` and returns its ring, or `None` if it was not
+    /// there. Its keys move to the other clusters.
+    pub fn remove_cluster(&mut self, cluster: &C) -> Option<HashRing<N, S>> {
+        self.clusters.remove_node(cluster);
+        self.nodes.remove(cluster)
+    }
+
+    /// Returns the outer ring, which routes keys to clusters.
+    pub fn cluster_ring(&self) -> &HashRing<C, S> {
+        &self.clusters
+    }
+
+    /// Returns the ring of nodes in `cluster`.
+    pub fn cluster(&self, cluster: &C) -> Option<&HashRing<N, S>> {
+        self.nodes.get(cluster)
+    }
+
+    /// Returns the ring of nodes in `cluster` for adding or removing nodes,
+    /// which moves keys only between the nodes of that cluster.
+    pub fn cluster_mut(&mut self, cluster: &C) -> Option<&mut HashRing<N, S>> {
+        self.nodes.get_mut(cluster)
+    }
+
+    /// Returns the cluster `key` goes to and the node within it.
+    ///
+    /// The key is looked up as by [`HashRing::get_node`] on the outer ring,
+    /// and then `(cluster, key)` is looked up on its cluster's ring, so
+    /// `cluster(c).get_node(&(c, key))` finds the same node. Returns `None`
+    /// if there are no clusters, or if the key's cluster has no nodes.
+    pub fn get_node<K: Hash + ?Sized>(&self, key: &K) -> Option<(&C, &N)> {
+        let cluster = self.clusters.get_node(key)?;
+        let node = self.nodes.get(cluster)?.get_node(&(cluster, key))?;
+        Some((cluster, node))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::sample_keys;
+
+    fn cluster_of(nodes: [&'static str; 4]) -> HashRing<&'static str> {
+        let mut ring = HashRing::with_replicas(16);
+        ring.add_nodes(nodes);
+        ring
+    }
+
+    #[test]
+    fn keys_spread_over_every_node_of_both_clusters() {
+        let mut tiered = TieredRing::new();
+        tiered.add_cluster("east", cluster_of(["e1", "e2", "e3", "e4"]));
+        tiered.add_cluster("west", cluster_of(["w1", "w2", "w3", "w4"]));
+
+        let keys = sample_keys(127, 8_000);
+        let mut per_node: BTreeMap<&str, usize> = BTreeMap::new();
+        for key in &keys {
+            let (cluster, node) = tiered.get_node(key).unwrap();
+            assert_eq!(&node[..1], &cluster[..1]);
+            assert_eq!(tiered.get_node(key), Some((cluster, node)));
+            assert_eq!(
+                tiered.cluster(cluster).unwrap().get_node(&(cluster, key)),
+                Some(node)
+            );
+            *per_node.entry(node).or_insert(0) += 1;
+        }
+
+        // Each cluster owns one arc of the outer ring, so the keys it gets
+        // are far from a fair sample of the hash space. Only a routing key
+        // for the inner step that is independent of that arc gives each node
+        // the share of them its points own.
+        assert_eq!(per_node.len(), 8);
+        for cluster in ["east", "west"] {
+            let ring = tiered.cluster(&cluster).unwrap();
+            let total: usize = ring.nodes().map(|node| per_node[node]).sum();
+            for (node, share) in ring.load_distribution() {
+                let observed = per_node[node] as f64 / total as f64;
+                assert!(
+                    (observed - share).abs() < 0.05,
+                    "{node}: {observed} against {share}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn removing_a_node_moves_keys_only_within_its_cluster() {
+        let mut tiered = TieredRing::new();
+        tiered.add_cluster("east", cluster_of(["e1", "e2", "e3", "e4"]));
+        tiered.add_cluster("west", cluster_of(["w1", "w2", "w3", "w4"]));
+        let keys = sample_keys(1270, 2_000);
+        let before: Vec<_> = keys
+            .iter()
+            .map(|key| tiered.get_node(key).unwrap())
+            .map(|(c, n)| (*c, *n))
+            .collect();
+
+        tiered.cluster_mut(&"east").unwrap().remove_node(&"e2");
+        for (key, &(cluster, node)) in keys.iter().zip(&before) {
+            let (now_cluster, now_node) = tiered.get_node(key).unwrap();
+            assert_eq!(*now_cluster, cluster);
+            if node != "e2" {
+                assert_eq!(*now_node, node);
+            }
+        }
+    }
+}
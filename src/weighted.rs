@@ -0,0 +1,71 @@
+use core::hash::Hash;
+
+use crate::{HashRing, RingHasher};
+
+/// A node that knows its own weight, for
+/// [`HashRing::add_weighted`].
+///
+/// This keeps a node's capacity next to its identity instead of passing it
+/// alongside on every [`add_weighted_node`](HashRing::add_weighted_node).
+pub trait Weighted {
+    /// Returns the node's weight, the multiple of the ring's replicas it
+    /// gets as points. It has to be at least one.
+    fn weight(&self) -> u32;
+}
+
+impl<T, S> HashRing<T, S>
+where
+    T: Hash + Ord + Clone + Weighted,
+    S: RingHasher,
+{
+    /// Adds `node` to the ring with the weight it reports, as if by
+    /// [`add_weighted_node`](Self::add_weighted_node).
+    ///
+    /// The weight is read once, here. A node whose weight changes later
+    /// keeps the points it was given until it is added again.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `node.weight()` is zero.
+    pub fn add_weighted(&mut self, node: T) {
+        let weight = node.weight();
+        self.add_weighted_node(node, weight);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+    struct Server {
+        name: &'static str,
+        capacity: u32,
+    }
+
+    impl Weighted for Server {
+        fn weight(&self) -> u32 {
+            self.capacity
+        }
+    }
+
+    #[test]
+    fn add_weighted_gives_a_node_the_points_its_weight_asks_for() {
+        let mut ring = HashRing::with_replicas(10);
+        let small = Server {
+            name: "small",
+            capacity: 1,
+        };
+        let large = Server {
+            name: "large",
+            capacity: 4,
+        };
+        ring.add_weighted(small.clone());
+        ring.add_weighted(large.clone());
+
+        let points = |node: &Server| ring.iter().filter(|&(_, owner)| owner == node).count();
+        assert_eq!(points(&small), 10);
+        assert_eq!(points(&large), 40);
+        assert_eq!(ring.topology(), [(large, 4), (small, 1)]);
+    }
+}
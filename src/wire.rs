@@ -0,0 +1,336 @@
+use alloc::{string::String, vec::Vec};
+use core::{hash::Hash, mem::size_of};
+
+use crate::{HashRing, HashRingError, RingHasher};
+
+/// The version byte every encoding starts with, bumped whenever the layout
+/// changes.
+const VERSION: u8 = 3;
+
+/// The most points [`HashRing::from_bytes`] will place, so a short run of
+/// bytes claiming a huge replica count or weight is turned away instead of
+/// being laid out.
+const MAX_POINTS: usize = 1 << 24;
+
+/// A node type that can be written into the bytes of
+/// [`HashRing::to_bytes`] and read back by [`HashRing::from_bytes`].
+///
+/// Integers encode as their little-endian bytes, and strings and byte
+/// vectors as themselves.
+pub trait WireNode: Sized {
+    /// Appends the encoding of the node to `out`.
+    fn encode(&self, out: &mut Vec<u8>);
+
+    /// Decodes a node from exactly `bytes`, or returns `None` if they are
+    /// not the encoding of one.
+    fn decode(bytes: &[u8]) -> Option<Self>;
+}
+
+impl WireNode for Vec<u8> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self);
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        Some(bytes.to_vec())
+    }
+}
+
+impl WireNode for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.as_bytes());
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+macro_rules! impl_wire_node {
+    ($($int:ty),* $(,)?) => {$(
+        impl WireNode for $int {
+            fn encode(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+
+            fn decode(bytes: &[u8]) -> Option<Self> {
+                bytes.try_into().ok().map(<$int>::from_le_bytes)
+            }
+        }
+    )*};
+}
+
+impl_wire_node!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl<T, S> HashRing<T, S>
+where
+    T: Hash + Ord + Clone + WireNode,
+    S: RingHasher,
+    S::Position: Into<u128> + TryFrom<u128>,
+{
+    /// Encodes the ring compactly, for shipping it to another process.
+    ///
+    /// The encoding holds the width of the ring's positions, the replica
+    /// count, the [`salt`](Self::salt), a flag for
+    /// [double hashing](crate::HashRingBuilder::double_hashing) and each
+    /// node's bytes and weight, with numbers as LEB128 varints. A node's
+    /// points are left out whenever the receiving end can recompute them,
+    /// which it can as long as it hashes the same way, as it has to for
+    /// keys to be routed the same anyway; that keeps the encoding at a few
+    /// bytes per node however many points there are. Only the positions
+    /// that cannot be recomputed are written out, sorted and as the gaps
+    /// between them: those of a ring built with a
+    /// [`replica_key`](crate::HashRingBuilder::replica_key), those a node
+    /// inherited through [`replace_node`](Self::replace_node), and those
+    /// placed with [`add_node_at`](Self::add_node_at). The hasher, replica
+    /// key, health check, draining nodes and bounded-load counts are not
+    /// included.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.push(VERSION);
+        out.push(size_of::<S::Position>() as u8);
+        write_varint(&mut out, self.replicas as u128);
+        write_varint(&mut out, u128::from(self.salt));
+        out.push(u8::from(self.double_hashing));
+        write_varint(&mut out, self.nodes.len() as u128);
+
+        let mut node_bytes = Vec::new();
+        for (node, entry) in &self.nodes {
+            node_bytes.clear();
+            node.encode(&mut node_bytes);
+            write_varint(&mut out, node_bytes.len() as u128);
+            out.extend_from_slice(&node_bytes);
+            write_varint(&mut out, u128::from(entry.weight));
+
+            // A node always has at least one point, so a count of zero is
+            // free to mean the points are where the node hashes to.
+            let recomputable = self.replica_key.is_none()
+                && self
+                    .point_positions(node, entry.weight)
+                    .eq(entry.positions.iter().copied());
+            if recomputable {
+                write_varint(&mut out, 0);
+                continue;
+            }
+            let mut positions: Vec<u128> = entry
+                .positions
+                .iter()
+                .map(|&position| position.into())
+                .collect();
+            positions.sort_unstable();
+            write_varint(&mut out, positions.len() as u128);
+            let mut previous = 0;
+            for position in positions {
+                write_varint(&mut out, position - previous);
+                previous = position;
+            }
+        }
+        out
+    }
+
+    /// Rebuilds a ring from the output of [`to_bytes`](Self::to_bytes),
+    /// hashing with a default `S`.
+    ///
+    /// The ring has the same layout as the one encoded if that one hashed
+    /// the same way as a default `S` does.
+    ///
+    /// Returns [`HashRingError::InvalidBytes`] if `bytes` are not an
+    /// encoding of a ring with this node and position type, or if they
+    /// describe more than 2<sup>24</sup> points in all.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, HashRingError>
+    where
+        S: Default,
+    {
+        Self::decode(bytes).ok_or(HashRingError::InvalidBytes)
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self>
+    where
+        S: Default,
+    {
+        let mut reader = Reader { bytes };
+        if reader.take(2)? != [VERSION, size_of::<S::Position>() as u8] {
+            return None;
+        }
+        let replicas = usize::try_from(reader.varint()?).ok()?;
+        if replicas == 0 {
+            return None;
+        }
+
+        let salt = u64::try_from(reader.varint()?).ok()?;
+        let double_hashing = match reader.take(1)? {
+            [0] => false,
+            [1] => true,
+            _ => return None,
+        };
+
+        let mut ring = Self::with_replicas_and_hasher(replicas, S::default());
+        ring.salt = salt;
+        ring.double_hashing = double_hashing;
+        let mut points: usize = 0;
+        for _ in 0..reader.varint()? {
+            let len = usize::try_from(reader.varint()?).ok()?;
+            let node = T::decode(reader.take(len)?)?;
+            let weight = u32::try_from(reader.varint()?).ok()?;
+            if weight == 0 || ring.contains_node(&node) {
+                return None;
+            }
+
+            let count = usize::try_from(reader.varint()?).ok()?;
+            let node_points = match count {
+                0 => replicas.checked_mul(usize::try_from(weight).ok()?)?,
+                count => count,
+            };
+            points = points.checked_add(node_points)?;
+            if points > MAX_POINTS {
+                return None;
+            }
+            if count == 0 {
+                ring.insert_weighted_node(node, weight);
+                continue;
+            }
+            let mut positions = Vec::with_capacity(count.min(reader.bytes.len()));
+            let mut position: u128 = 0;
+            for _ in 0..count {
+                position = position.checked_add(reader.varint()?)?;
+                positions.push(S::Position::try_from(position).ok()?);
+            }
+            ring.place_node(node, weight, positions);
+        }
+        reader.bytes.is_empty().then_some(ring)
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u128) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if len > self.bytes.len() {
+            return None;
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Some(taken)
+    }
+
+    fn varint(&mut self) -> Option<u128> {
+        let mut value: u128 = 0;
+        for shift in (0..128).step_by(7) {
+            let byte = self.take(1)?[0];
+            let bits = u128::from(byte & 0x7f);
+            if bits.checked_shl(shift)? >> shift != bits {
+                return None;
+            }
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{format, string::ToString, vec};
+
+    use super::*;
+    use crate::sample_keys;
+
+    /// Encodes a ring with a single node of `weight`, whose points are to be
+    /// recomputed, on a ring of `replicas` replicas.
+    fn one_node_ring(replicas: u128, weight: u128) -> Vec<u8> {
+        let mut bytes = vec![VERSION, 8];
+        write_varint(&mut bytes, replicas);
+        write_varint(&mut bytes, 0);
+        bytes.push(0);
+        write_varint(&mut bytes, 1);
+        write_varint(&mut bytes, 4);
+        7u32.encode(&mut bytes);
+        write_varint(&mut bytes, weight);
+        write_varint(&mut bytes, 0);
+        bytes
+    }
+
+    #[test]
+    fn a_decoded_ring_routes_as_the_encoded_one() {
+        let mut ring: HashRing<String> = HashRing::with_replicas(32);
+        for node in 0..50 {
+            ring.add_weighted_node(format!("node-{node}"), node % 3 + 1);
+        }
+        ring.add_node_at("pinned".to_string(), 12_345);
+        ring.replace_node(&"node-7".to_string(), "node-7b".to_string());
+
+        let decoded = HashRing::<String>::from_bytes(&ring.to_bytes()).unwrap();
+        assert_eq!(decoded, ring);
+        assert_eq!(decoded.topology(), ring.topology());
+        for key in sample_keys(73, 2_000) {
+            assert_eq!(decoded.get_node(&key), ring.get_node(&key));
+        }
+    }
+
+    #[test]
+    fn the_encoding_is_far_smaller_than_json() {
+        let mut ring: HashRing<String> = HashRing::with_replicas(160);
+        ring.add_nodes((0..1_000).map(|node| format!("10.0.{}.{}:11211", node / 256, node % 256)));
+
+        // What serializing the points and settings as JSON would produce.
+        let points: Vec<String> = ring
+            .iter()
+            .map(|(position, node)| format!("[{position},\"{node}\"]"))
+            .collect();
+        let json = format!(
+            "{{\"replicas\":{},\"salt\":{},\"points\":[{}]}}",
+            ring.replicas(),
+            ring.salt(),
+            points.join(",")
+        );
+
+        let bytes = ring.to_bytes();
+        assert!(
+            bytes.len() * 100 < json.len(),
+            "{} vs {}",
+            bytes.len(),
+            json.len()
+        );
+    }
+
+    #[test]
+    fn oversized_encodings_are_turned_away() {
+        let within = one_node_ring(1 << 10, 1 << 4);
+        assert_eq!(
+            HashRing::<u32>::from_bytes(&within).map(|ring| ring.iter().len()),
+            Ok(1 << 14)
+        );
+
+        for (replicas, weight) in [
+            (1 << 20, 1 << 5),
+            (u128::from(u64::MAX), u128::from(u32::MAX)),
+            (1, u128::from(u32::MAX)),
+        ] {
+            assert_eq!(
+                HashRing::<u32>::from_bytes(&one_node_ring(replicas, weight)),
+                Err(HashRingError::InvalidBytes)
+            );
+        }
+
+        // A count of explicit positions that nothing could back.
+        let mut bytes = one_node_ring(1, 1);
+        bytes.pop();
+        write_varint(&mut bytes, MAX_POINTS as u128 + 1);
+        assert_eq!(
+            HashRing::<u32>::from_bytes(&bytes),
+            Err(HashRingError::InvalidBytes)
+        );
+    }
+}
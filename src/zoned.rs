@@ -0,0 +1,102 @@
+use alloc::vec::Vec;
+use core::hash::Hash;
+
+use crate::{HashRing, RingHasher};
+
+/// A node that knows which zone it runs in, such as a rack or an
+/// availability zone, for [`HashRing::get_zone_aware_replicas`].
+pub trait Zoned {
+    /// The type zones are told apart by.
+    type Zone: Eq;
+
+    /// Returns the zone the node is in.
+    fn zone(&self) -> Self::Zone;
+}
+
+impl<T, S> HashRing<T, S>
+where
+    T: Hash + Ord + Clone + Zoned,
+    S: RingHasher,
+{
+    /// Returns up to `count` distinct nodes for `key`, spread over as many
+    /// zones as the ring has, so losing a zone loses as few copies as it
+    /// can.
+    ///
+    /// The walk is the one [`get_nodes`](Self::get_nodes) makes, starting
+    /// with the node that owns `key`, except that a node is passed over
+    /// while another zone has gone longer without a node in the result.
+    /// So the first nodes are from different zones until every zone has
+    /// one, after which the zones take turns, and with nodes in two zones
+    /// the result alternates between them for as long as both have nodes
+    /// left. Within a zone, nodes come in the order the walk reaches them.
+    pub fn get_zone_aware_replicas<K: Hash + ?Sized>(&self, key: &K, count: usize) -> Vec<&T> {
+        let mut remaining = self.nodes_clockwise_from(key);
+        let mut replicas = Vec::with_capacity(count.min(remaining.len()));
+        // Each zone picked from, with the number of nodes picked before its
+        // latest.
+        let mut last_picked: Vec<(T::Zone, usize)> = Vec::new();
+        while replicas.len() < count && !remaining.is_empty() {
+            let (index, zone) = remaining
+                .iter()
+                .map(|node| node.zone())
+                .enumerate()
+                .min_by_key(|(_, zone)| {
+                    last_picked
+                        .iter()
+                        .find(|(picked, _)| picked == zone)
+                        .map_or(0, |&(_, at)| at + 1)
+                })
+                .unwrap();
+            match last_picked.iter_mut().find(|(picked, _)| *picked == zone) {
+                Some((_, at)) => *at = replicas.len(),
+                None => last_picked.push((zone, replicas.len())),
+            }
+            replicas.push(remaining.remove(index));
+        }
+        replicas
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sample_keys;
+
+    /// A node named by a zone letter and a number, such as `"a1"`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    struct Node(&'static str);
+
+    impl Zoned for Node {
+        type Zone = u8;
+
+        fn zone(&self) -> u8 {
+            self.0.as_bytes()[0]
+        }
+    }
+
+    #[test]
+    fn replicas_alternate_between_two_zones() {
+        let mut ring = HashRing::with_replicas(16);
+        ring.add_nodes(["a1", "a2", "a3", "a4", "b1", "b2"].map(Node));
+
+        for key in sample_keys(111, 200) {
+            let replicas = ring.get_zone_aware_replicas(&key, 6);
+            assert_eq!(replicas.first().copied(), ring.get_node(&key));
+            let zones: Vec<u8> = replicas.iter().map(|node| node.zone()).collect();
+
+            // Both zones take turns until the second runs out of nodes,
+            // and then the rest come from the first.
+            assert_eq!(zones.len(), 6);
+            assert!(
+                zones[..4].windows(2).all(|pair| pair[0] != pair[1]),
+                "{zones:?}"
+            );
+            assert_eq!(&zones[4..], b"aa");
+
+            let mut distinct = replicas.clone();
+            distinct.sort();
+            distinct.dedup();
+            assert_eq!(distinct.len(), 6);
+        }
+    }
+}